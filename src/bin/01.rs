@@ -3,59 +3,53 @@ use anyhow::Result;
 const DAY: u8 = 01;
 
 fn part_1(input: &str) -> Result<u32> {
-    Ok(input
-        .lines()
-        .map(|l| {
-            let mut d = l.chars().filter_map(|c| c.to_digit(10));
-            match (d.next(), d.next_back()) {
-                (Some(a), Some(b)) => a * 10 + b,
-                (Some(a), None) => a * 10 + a,
-                _ => unreachable!("Invalid input"),
-            }
-        })
-        .sum())
+    Ok(aoc::par::sum_lines(input, |l| {
+        let mut d = l.chars().filter_map(|c| c.to_digit(10));
+        match (d.next(), d.next_back()) {
+            (Some(a), Some(b)) => a * 10 + b,
+            (Some(a), None) => a * 10 + a,
+            _ => unreachable!("Invalid input"),
+        }
+    }))
 }
 
 fn part_2(input: &str) -> Result<u32> {
     let lookup = [
         "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine",
     ];
-    Ok(input
-        .lines()
-        .map(|mut l| {
-            let first;
-            let last;
-            let mut chars = l.chars().peekable();
-            'o: loop {
-                if let Some(d) = chars.peek().unwrap().to_digit(10) {
-                    first = d;
-                    break;
-                }
-                chars.next().unwrap();
-                for (n, &pat) in lookup.iter().enumerate() {
-                    if l.starts_with(pat) {
-                        first = n as u32;
-                        break 'o;
-                    }
-                }
-                l = &l[1..];
+    Ok(aoc::par::sum_lines(input, |mut l| {
+        let first;
+        let last;
+        let mut chars = l.chars().peekable();
+        'o: loop {
+            if let Some(d) = chars.peek().unwrap().to_digit(10) {
+                first = d;
+                break;
             }
-            'o: loop {
-                if let Some(d) = chars.next_back().unwrap().to_digit(10) {
-                    last = d;
-                    break;
+            chars.next().unwrap();
+            for (n, &pat) in lookup.iter().enumerate() {
+                if l.starts_with(pat) {
+                    first = n as u32;
+                    break 'o;
                 }
-                for (n, &pat) in lookup.iter().enumerate() {
-                    if l.ends_with(pat) {
-                        last = n as u32;
-                        break 'o;
-                    }
+            }
+            l = &l[1..];
+        }
+        'o: loop {
+            if let Some(d) = chars.next_back().unwrap().to_digit(10) {
+                last = d;
+                break;
+            }
+            for (n, &pat) in lookup.iter().enumerate() {
+                if l.ends_with(pat) {
+                    last = n as u32;
+                    break 'o;
                 }
-                l = &l[..l.len() - 1];
             }
-            first * 10 + last
-        })
-        .sum())
+            l = &l[..l.len() - 1];
+        }
+        first * 10 + last
+    }))
 }
 
 fn main() -> Result<()> {
@@ -5,42 +5,38 @@ use itertools::Itertools;
 
 const DAY: u8 = 04;
 
-fn part_1(input: &str) -> Result<u32> {
-    Ok(input
-        .lines()
-        .map(|l| l.split(": ").nth(1).unwrap())
-        .map(|l| {
-            l.split(" | ")
-                .map(|nums| nums.split(' ').filter(|&n| n != "").collect::<HashSet<_>>())
-                .collect_tuple()
-                .unwrap()
+fn count_matches(line: &str) -> u32 {
+    let (winning, ours) = line
+        .split(": ")
+        .nth(1)
+        .unwrap()
+        .split(" | ")
+        .map(|nums| {
+            nums.split(' ')
+                .filter(|n| !n.is_empty())
+                .collect::<HashSet<_>>()
         })
-        .map(|(winning, ours)| winning.intersection(&ours).count() as u32)
-        .filter(|&count| count > 0)
-        .map(|count| 1 << (count - 1))
-        .sum())
+        .collect_tuple()
+        .unwrap();
+    winning.intersection(&ours).count() as u32
+}
+
+fn part_1(input: &str) -> Result<u32> {
+    Ok(aoc::par::sum_lines(input, |l| match count_matches(l) {
+        0 => 0,
+        count => 1 << (count - 1),
+    }))
 }
 
 fn part_2(input: &str) -> Result<u32> {
     let mut sum = 0;
-    let mut next_count = input
-        .lines()
-        .map(|l| l.split(": ").nth(1).unwrap())
-        .map(|l| {
-            l.split(" | ")
-                .map(|nums| nums.split(' ').filter(|&n| n != "").collect::<HashSet<_>>())
-                .collect_tuple()
-                .unwrap()
-        })
-        .map(|(winning, ours)| winning.intersection(&ours).count() as u32);
-    let mut counts = vec![1; input.lines().count()];
-    let mut i = 0;
-    while let Some(count) = next_count.next() {
+    let matches = aoc::par::map_lines(input, count_matches);
+    let mut counts = vec![1; matches.len()];
+    for (i, count) in matches.into_iter().enumerate() {
         sum += counts[i];
         for j in 0..(count as usize).min(counts.len()) {
             counts[i + j + 1] += counts[i];
         }
-        i += 1;
     }
     Ok(sum)
 }
@@ -120,4 +120,57 @@ mod tests {
         aoc::assert_output_matches_str(DAY, "example2", part_2, res)?;
         Ok(())
     }
+
+    /// Maps every single seed through every map line.
+    fn brute_force(input: &str, ranges: bool) -> i64 {
+        let mut sections = input.split("\n\n");
+        let seeds = sections.next().unwrap()[7..]
+            .split(' ')
+            .map(|s| s.parse::<i64>().unwrap())
+            .collect_vec();
+        let maps = sections
+            .map(|s| {
+                s.lines()
+                    .skip(1)
+                    .map(|l| {
+                        l.split(' ')
+                            .map(|n| n.parse::<i64>().unwrap())
+                            .collect_tuple::<(_, _, _)>()
+                            .unwrap()
+                    })
+                    .collect_vec()
+            })
+            .collect_vec();
+        let location = |mut n: i64| {
+            for map in &maps {
+                if let Some((dst, src, _)) = map
+                    .iter()
+                    .find(|&&(_, src, len)| (src..src + len).contains(&n))
+                {
+                    n += dst - src;
+                }
+            }
+            n
+        };
+        if ranges {
+            seeds
+                .chunks(2)
+                .flat_map(|c| c[0]..c[0] + c[1])
+                .map(location)
+                .min()
+                .unwrap()
+        } else {
+            seeds.into_iter().map(location).min().unwrap()
+        }
+    }
+
+    #[test]
+    fn test_brute_force() -> Result<()> {
+        for seed in 0..30 {
+            let input = aoc::generate::generate(DAY, 1 + seed as usize % 8, seed)?;
+            assert_eq!(part_1(&input)?, brute_force(&input, false), "{}", input);
+            assert_eq!(part_2(&input)?, brute_force(&input, true), "{}", input);
+        }
+        Ok(())
+    }
 }
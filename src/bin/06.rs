@@ -1,56 +1,139 @@
 use std::iter::zip;
 
-use anyhow::Result;
-use itertools::Itertools;
+use anyhow::{Context, Result};
 
 const DAY: u8 = 06;
 
-fn part_1(input: &str) -> Result<u32> {
-    let (times, distances) = input.lines().collect_tuple().unwrap();
-    Ok(zip(
-        times.split_ascii_whitespace(),
-        distances.split_ascii_whitespace(),
-    )
-    .skip(1)
-    .map(|(t, d)| (t.parse::<f32>().unwrap(), d.parse::<f32>().unwrap()))
-    .map(|(t, d)| {
-        let discriminant = t * t - 4.0 * d;
-        (
-            (t + discriminant.sqrt()) / 2.0,
-            (t - discriminant.sqrt()) / 2.0,
-        )
-    })
-    .map(|(a, b)| (a.ceil() - b.floor()) as u32 - 1)
-    .product())
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Race {
+    time: u64,
+    record: u64,
+}
+
+/// Hold times that beat the record, both inclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Holds {
+    min: u64,
+    max: u64,
+}
+
+impl Holds {
+    fn count(&self) -> u64 {
+        self.max - self.min + 1
+    }
+}
+
+impl Race {
+    fn distance(&self, hold: u64) -> u128 {
+        hold as u128 * (self.time - hold) as u128
+    }
+
+    fn beats(&self, hold: u64) -> bool {
+        self.distance(hold) > self.record as u128
+    }
+
+    /// Solves `hold * (time - hold) > record` exactly, `None` if the record cannot be beaten.
+    fn winning_holds(&self) -> Option<Holds> {
+        let (t, d) = (self.time as u128, self.record as u128);
+        // the distance is symmetric around the middle, where it is the largest
+        if !self.beats(self.time / 2) {
+            return None;
+        }
+        // smaller root is (t - sqrt(t^2 - 4d)) / 2, both roundings are fixed below
+        let root = (t * t - 4 * d).isqrt();
+        let mut min = ((t - root) / 2) as u64;
+        while !self.beats(min) {
+            min += 1;
+        }
+        while min > 0 && self.beats(min - 1) {
+            min -= 1;
+        }
+        Some(Holds {
+            min,
+            max: self.time - min,
+        })
+    }
+
+    fn ways_to_win(&self) -> u128 {
+        self.winning_holds().map_or(0, |h| h.count() as u128)
+    }
 }
 
-fn part_2(input: &str) -> Result<u32> {
-    let (time, distance) = input
-        .lines()
-        .map(|l| {
-            l.split_ascii_whitespace()
-                .skip(1)
-                .join("")
-                .parse::<f64>()
-                .unwrap()
+fn parse_numbers<'a>(line: Option<&'a str>, name: &str) -> Result<Vec<&'a str>> {
+    let line = line.with_context(|| format!("Missing {} line", name))?;
+    let Some(numbers) = line.strip_prefix(name).and_then(|l| l.strip_prefix(':')) else {
+        anyhow::bail!("Expected {}, got: {}", name, line);
+    };
+    Ok(numbers.split_ascii_whitespace().collect())
+}
+
+fn parse_races(input: &str) -> Result<Vec<Race>> {
+    let mut lines = input.lines();
+    let times = parse_numbers(lines.next(), "Time")?;
+    let distances = parse_numbers(lines.next(), "Distance")?;
+    if times.len() != distances.len() {
+        anyhow::bail!("{} times, but {} distances", times.len(), distances.len());
+    }
+    zip(times, distances)
+        .map(|(t, d)| {
+            Ok(Race {
+                time: t.parse()?,
+                record: d.parse()?,
+            })
         })
-        .collect_tuple()
-        .unwrap();
-    let discriminant = time * time - 4.0 * distance;
-    let (a, b) = (
-        (time + discriminant.sqrt()) / 2.0,
-        (time - discriminant.sqrt()) / 2.0,
-    );
-    Ok((a.ceil() - b.floor()) as u32 - 1)
+        .collect()
+}
+
+/// All the races of the sheet as one long race, the kerning is wrong.
+fn parse_single_race(input: &str) -> Result<Race> {
+    let mut lines = input.lines();
+    Ok(Race {
+        time: parse_numbers(lines.next(), "Time")?.concat().parse()?,
+        record: parse_numbers(lines.next(), "Distance")?.concat().parse()?,
+    })
+}
+
+fn part_1(input: &str) -> Result<u128> {
+    Ok(parse_races(input)?.iter().map(Race::ways_to_win).product())
+}
+
+fn part_2(input: &str) -> Result<u128> {
+    Ok(parse_single_race(input)?.ways_to_win())
+}
+
+fn holds(input: &str) -> Result<()> {
+    let races = parse_races(input)?;
+    for race in races.iter().chain([&parse_single_race(input)?]) {
+        match race.winning_holds() {
+            Some(h) => println!(
+                "time {} record {}: hold {}..={} ({} ways)",
+                race.time,
+                race.record,
+                h.min,
+                h.max,
+                h.count()
+            ),
+            None => println!("time {} record {}: cannot win", race.time, race.record),
+        }
+    }
+    Ok(())
 }
 
 fn main() -> Result<()> {
-    aoc::solve_all(DAY, part_1, part_2)?;
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        ["holds"] => holds(&aoc::read_input(DAY, None)?)?,
+        ["holds", "--input", suffix] => holds(&aoc::read_input(DAY, Some(suffix))?)?,
+        [] => aoc::solve_all(DAY, part_1, part_2)?,
+        _ => anyhow::bail!("Usage: [holds [--input <suffix>]]"),
+    }
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
+    use itertools::Itertools;
+
     use super::*;
 
     #[test]
@@ -66,4 +149,82 @@ mod tests {
         aoc::assert_output_matches_str(DAY, "example2", part_2, res)?;
         Ok(())
     }
+
+    fn brute_force(time: u128, distance: u128) -> u128 {
+        (0..=time).filter(|h| h * (time - h) > distance).count() as u128
+    }
+
+    #[test]
+    fn test_brute_force() -> Result<()> {
+        for seed in 0..100 {
+            let input = aoc::generate::generate(DAY, 1 + seed as usize % 2, seed)?;
+            let (times, distances) = input
+                .lines()
+                .map(|l| {
+                    l.split_ascii_whitespace()
+                        .skip(1)
+                        .map(|n| n.parse::<u128>().unwrap())
+                        .collect_vec()
+                })
+                .collect_tuple()
+                .unwrap();
+            let expected = zip(&times, &distances)
+                .map(|(&t, &d)| brute_force(t, d))
+                .product::<u128>();
+            assert_eq!(part_1(&input)?, expected, "{}", input);
+            let join = |nums: &[u128]| nums.iter().join("").parse::<u128>().unwrap();
+            let expected = brute_force(join(&times), join(&distances));
+            assert_eq!(part_2(&input)?, expected, "{}", input);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_winning_holds() {
+        let race = |time, record| Race { time, record };
+        assert_eq!(race(7, 9).winning_holds(), Some(Holds { min: 2, max: 5 }));
+        assert_eq!(
+            race(30, 200).winning_holds(),
+            Some(Holds { min: 11, max: 19 })
+        );
+        assert_eq!(race(71530, 940200).ways_to_win(), 71503);
+        // perfect square discriminants, the roots themselves only tie the record
+        assert_eq!(race(10, 21).winning_holds(), Some(Holds { min: 4, max: 6 }));
+        assert_eq!(race(10, 24).winning_holds(), Some(Holds { min: 5, max: 5 }));
+        assert_eq!(race(10, 25).winning_holds(), None);
+        assert_eq!(race(10, 0).winning_holds(), Some(Holds { min: 1, max: 9 }));
+        assert_eq!(race(0, 0).winning_holds(), None);
+        assert_eq!(race(1, 0).winning_holds(), None);
+    }
+
+    #[test]
+    fn test_large_race() {
+        // 2^20 * (2^40 - 2^20) = 2^60 - 2^40, the roots are 2^20 and 2^40 - 2^20
+        let race = Race {
+            time: 1 << 40,
+            record: (1 << 60) - (1 << 40),
+        };
+        let holds = race.winning_holds().unwrap();
+        assert_eq!(
+            holds,
+            Holds {
+                min: (1 << 20) + 1,
+                max: (1 << 40) - (1 << 20) - 1
+            }
+        );
+        assert!(!race.beats(holds.min - 1) && race.beats(holds.min));
+        // one less and the roots themselves win. This is the case that breaks f64:
+        // t^2 - 4d = (2^40 - 2^21)^2 + 4 needs 79 bits, so the + 4 is rounded away,
+        // the square root comes out exact and the roots look like ties, not wins
+        let race = Race {
+            record: race.record - 1,
+            ..race
+        };
+        assert_eq!(race.winning_holds().unwrap().min, 1 << 20);
+        let race = Race {
+            time: u64::MAX,
+            record: 0,
+        };
+        assert_eq!(race.ways_to_win(), u64::MAX as u128 - 1);
+    }
 }
@@ -1,149 +1,260 @@
-use std::{collections::BTreeMap, str::FromStr};
+use std::fmt::Display;
 
 use anyhow::Result;
+use aoc::Flags;
 use itertools::Itertools;
 
 const DAY: u8 = 07;
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
-struct Card(u8);
+/// Card labels of the standard deck, weakest first.
+const LABELS: &str = "23456789TJQKA";
+/// Most cards a hand can hold, so hands fit in fixed-size arrays.
+const MAX_HAND: usize = 16;
 
-impl FromStr for Card {
-    type Err = anyhow::Error;
+/// How a game of Camel Cards is played.
+#[derive(Debug, Clone)]
+struct Rules {
+    /// Strength of every card label, `None` for labels not in the deck.
+    strength: [Option<u8>; 256],
+    wild: [bool; 256],
+    hand_size: usize,
+}
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if s.len() != 1 {
-            anyhow::bail!("Invalid card: {}", s);
+impl Rules {
+    /// `order` lists the labels weakest first. The `wild` ones act like whatever card
+    /// makes the best hand, but are weaker than any other card when breaking ties.
+    fn new(order: &str, wild: &str, hand_size: usize) -> Result<Self> {
+        if hand_size == 0 {
+            anyhow::bail!("Hands need at least one card");
+        }
+        if hand_size > MAX_HAND {
+            anyhow::bail!("Hands hold at most {} cards", MAX_HAND);
+        }
+        let mut rules = Self {
+            strength: [None; 256],
+            wild: [false; 256],
+            hand_size,
+        };
+        for c in wild.bytes() {
+            if !order.as_bytes().contains(&c) {
+                anyhow::bail!("Wild card {} is not in the deck", c as char);
+            }
+            rules.wild[c as usize] = true;
+        }
+        let (wild, normal): (Vec<_>, Vec<_>) = order.bytes().partition(|&c| rules.wild[c as usize]);
+        for (strength, c) in wild.into_iter().chain(normal).enumerate() {
+            if rules.strength[c as usize].is_some() {
+                anyhow::bail!("Card {} is in the deck twice", c as char);
+            }
+            rules.strength[c as usize] = Some(strength as u8);
+        }
+        Ok(rules)
+    }
+
+    fn standard() -> Self {
+        Self::new(LABELS, "", 5).unwrap()
+    }
+
+    fn jokers() -> Self {
+        Self::new(LABELS, "J", 5).unwrap()
+    }
+
+    fn parse_hand(&self, s: &str) -> Result<Hand> {
+        if s.len() != self.hand_size {
+            anyhow::bail!("Invalid hand: {}", s);
+        }
+        let mut cards = [0; MAX_HAND];
+        // strengths of the cards that are not wild, sorted so equal cards are adjacent
+        let mut sorted = [0; MAX_HAND];
+        let mut normal = 0;
+        for (i, c) in s.bytes().enumerate() {
+            let Some(strength) = self.strength[c as usize] else {
+                anyhow::bail!("Invalid card: {}", c as char);
+            };
+            cards[i] = strength;
+            if !self.wild[c as usize] {
+                sorted[normal] = strength;
+                normal += 1;
+            }
+        }
+        sorted[..normal].sort_unstable();
+        let mut counts = [0; MAX_HAND];
+        for (i, group) in sorted[..normal].chunk_by(|a, b| a == b).enumerate() {
+            counts[i] = group.len() as u8;
         }
-        let ch = s.chars().next().unwrap();
-        Ok(Self(match ch {
-            '2' => 2,
-            '3' => 3,
-            '4' => 4,
-            '5' => 5,
-            '6' => 6,
-            '7' => 7,
-            '8' => 8,
-            '9' => 9,
-            'T' => 10,
-            'J' => 11,
-            'Q' => 12,
-            'K' => 13,
-            'A' => 14,
-            _ => anyhow::bail!("Invalid card: {}", s),
-        }))
+        Ok(Hand {
+            t: HandType::new(counts, (self.hand_size - normal) as u8),
+            cards,
+        })
+    }
+
+    /// `hand` with every wild card replaced by the card that makes the best hand.
+    /// The hand **must** be valid.
+    fn best_hand(&self, hand: &str) -> String {
+        let strength = |c: u8| self.strength[c as usize];
+        let target = hand
+            .bytes()
+            .filter(|&c| !self.wild[c as usize])
+            .counts()
+            .into_iter()
+            .max_by_key(|&(c, n)| (n, strength(c)))
+            .map(|(c, _)| c)
+            .or_else(|| {
+                // all wild, so become the strongest of the other cards
+                (0..=255u8)
+                    .filter(|&c| strength(c).is_some() && !self.wild[c as usize])
+                    .max_by_key(|&c| strength(c))
+            });
+        hand.bytes()
+            .map(|c| match target {
+                Some(t) if self.wild[c as usize] => t as char,
+                _ => c as char,
+            })
+            .collect()
     }
 }
 
+/// Sizes of the groups of equal cards, largest first. Comparing them orders the hands
+/// from high card up to five of a kind, and works just the same for any hand size.
+/// Unused entries are zero, so they sort after every real group.
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
-enum HandType {
-    HighCard,
-    OnePair,
-    TwoPair,
-    ThreeOfAKind,
-    FullHouse,
-    FourOfAKind,
-    FiveOfAKind,
+struct HandType([u8; MAX_HAND]);
+
+impl HandType {
+    fn new(mut counts: [u8; MAX_HAND], wild: u8) -> Self {
+        counts.sort_unstable_by(|a, b| b.cmp(a));
+        // wild cards always do best joining the largest group, which is empty if all are wild
+        counts[0] += wild;
+        Self(counts)
+    }
+
+    /// Sizes of the groups, without the unused entries.
+    fn groups(&self) -> &[u8] {
+        let len = self.0.iter().position(|&n| n == 0).unwrap_or(MAX_HAND);
+        &self.0[..len]
+    }
+}
+
+impl Display for HandType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.groups() {
+            [5] => write!(f, "Five of a kind"),
+            [4, 1] => write!(f, "Four of a kind"),
+            [3, 2] => write!(f, "Full house"),
+            [3, 1, 1] => write!(f, "Three of a kind"),
+            [2, 2, 1] => write!(f, "Two pair"),
+            [2, 1, 1, 1] => write!(f, "One pair"),
+            [1, 1, 1, 1, 1] => write!(f, "High card"),
+            groups => write!(f, "Groups {}", groups.iter().join("+")),
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash)]
 struct Hand {
     t: HandType,
-    cards: [Card; 5],
+    /// Card strengths, in the order they were dealt, padded with zeros.
+    cards: [u8; MAX_HAND],
 }
 
-impl FromStr for Hand {
-    type Err = anyhow::Error;
+fn parse_line(line: &str, rules: &Rules) -> Result<(Hand, u64)> {
+    let Some((hand, bid)) = line.split_once(' ') else {
+        anyhow::bail!("Invalid line: {}", line);
+    };
+    Ok((rules.parse_hand(hand)?, bid.parse()?))
+}
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if s.len() != 5 {
-            anyhow::bail!("Invalid hand: {}", s);
-        }
-        let mut cards: [Card; 5] = [Card(0); 5];
-        for i in 0..5 {
-            cards[i] = s[i..i + 1].parse()?
-        }
-        let set = cards.iter().fold(BTreeMap::new(), |mut set, &c| {
-            *set.entry(c).or_insert(0) += 1;
-            set
-        });
-        let t = match set.len() {
-            5 => HandType::HighCard,
-            4 => HandType::OnePair,
-            3 => {
-                if set.values().any(|&v| v == 3) {
-                    HandType::ThreeOfAKind
-                } else {
-                    HandType::TwoPair
-                }
-            }
-            2 => {
-                if set.values().any(|&v| v == 4) {
-                    HandType::FourOfAKind
-                } else {
-                    HandType::FullHouse
-                }
-            }
-            1 => HandType::FiveOfAKind,
-            _ => anyhow::bail!("Invalid hand: {}", s),
-        };
-        Ok(Self { t, cards })
-    }
+/// All the hands with their bids and labels, weakest (rank 1) first.
+fn ranked<'a>(input: &'a str, rules: &Rules) -> Result<Vec<(Hand, u64, &'a str)>> {
+    let mut hands = aoc::par::map_lines(input, |l| parse_line(l, rules))
+        .into_iter()
+        .zip(input.lines())
+        .map(|(parsed, l)| parsed.map(|(hand, bid)| (hand, bid, &l[..rules.hand_size])))
+        .collect::<Result<Vec<_>>>()?;
+    hands.sort();
+    Ok(hands)
 }
 
-fn part_1(input: &str) -> Result<u32> {
-    Ok(input
-        .lines()
-        .map(|l| l.split(' ').collect_tuple().unwrap())
-        .map(|(hand, bid)| (hand.parse::<Hand>().unwrap(), bid.parse::<u32>().unwrap()))
-        .sorted()
+fn total_winnings(input: &str, rules: &Rules) -> Result<u64> {
+    Ok(ranked(input, rules)?
+        .into_iter()
         .enumerate()
-        .map(|(i, (_, bid))| bid * (i as u32 + 1))
+        .map(|(i, (_, bid, _))| bid * (i as u64 + 1))
         .sum())
 }
 
-fn jokerify(mut h: Hand) -> Hand {
-    const JOKER: Card = Card(11);
-    let mut joker_count = 0;
-    for c in h.cards.iter_mut() {
-        if *c == JOKER {
-            *c = Card(1);
-            joker_count += 1;
-        }
+/// Table of every hand with its type, best hand, rank and winnings.
+fn explain(input: &str, rules: &Rules) -> Result<String> {
+    let width = rules.hand_size.max(4);
+    let mut out = format!(
+        "{:>5}  {:<width$}  {:<15}  {:<width$}  {:>6}  {:>10}\n",
+        "rank", "hand", "type", "best", "bid", "winnings"
+    );
+    let mut total = 0;
+    for (i, (hand, bid, labels)) in ranked(input, rules)?.into_iter().enumerate() {
+        let winnings = bid * (i as u64 + 1);
+        total += winnings;
+        out += &format!(
+            "{:>5}  {:<width$}  {:<15}  {:<width$}  {:>6}  {:>10}\n",
+            i + 1,
+            labels,
+            hand.t.to_string(),
+            rules.best_hand(labels),
+            bid,
+            winnings
+        );
     }
-    if joker_count == 0 {
-        return h;
-    }
-    h.t = match h.t {
-        HandType::HighCard => HandType::OnePair,
-        HandType::OnePair => HandType::ThreeOfAKind,
-        HandType::TwoPair => {
-            if joker_count == 2 {
-                HandType::FourOfAKind
-            } else {
-                HandType::FullHouse
-            }
-        }
-        HandType::ThreeOfAKind => HandType::FourOfAKind,
-        _ => HandType::FiveOfAKind,
-    };
-    h
+    out += &format!("total {}\n", total);
+    Ok(out)
 }
 
-fn part_2(input: &str) -> Result<u32> {
-    Ok(input
-        .lines()
-        .map(|l| l.split(' ').collect_tuple().unwrap())
-        .map(|(hand, bid)| (hand.parse::<Hand>().unwrap(), bid.parse::<u32>().unwrap()))
-        .map(|(hand, bid)| (jokerify(hand), bid))
-        .sorted()
-        .enumerate()
-        .map(|(i, (_, bid))| bid * (i as u32 + 1))
-        .sum())
+fn part_1(input: &str) -> Result<u64> {
+    total_winnings(input, &Rules::standard())
+}
+
+fn part_2(input: &str) -> Result<u64> {
+    total_winnings(input, &Rules::jokers())
+}
+
+/// Rules and input suffix from `[--order <labels>] [--wild <labels>] [--size <n>] [--input <suffix>]`.
+fn parse_rules(args: &[String]) -> Result<(Rules, Option<&str>)> {
+    const USAGE: &str = "Usage: play|explain [--order <labels>] [--wild <labels>] \
+                         [--size <n>] [--input <suffix>]";
+    let flags = Flags::parse(
+        args,
+        &["--order", "--wild", "--size", "--input"],
+        &[],
+        USAGE,
+    )?;
+    flags.positional::<0>()?;
+    let rules = Rules::new(
+        flags.value("--order").unwrap_or(LABELS),
+        flags.value("--wild").unwrap_or(""),
+        flags.parsed_or("--size", 5)?,
+    )?;
+    Ok((rules, flags.input()))
 }
 
 fn main() -> Result<()> {
-    aoc::solve_all(DAY, part_1, part_2)?;
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    match args.first().map(String::as_str) {
+        Some("play") => {
+            let (rules, suffix) = parse_rules(&args[1..])?;
+            println!(
+                "{}",
+                total_winnings(&aoc::read_input(DAY, suffix)?, &rules)?
+            );
+        }
+        Some("explain") => {
+            let (rules, suffix) = parse_rules(&args[1..])?;
+            print!("{}", explain(&aoc::read_input(DAY, suffix)?, &rules)?);
+        }
+        None => aoc::solve_all(DAY, part_1, part_2)?,
+        _ => anyhow::bail!(
+            "Usage: [play|explain [--order <labels>] [--wild <labels>] \
+             [--size <n>] [--input <suffix>]]"
+        ),
+    }
     Ok(())
 }
 
@@ -164,4 +275,70 @@ mod tests {
         aoc::assert_output_matches_str(DAY, "example2", part_2, res)?;
         Ok(())
     }
+
+    #[test]
+    fn test_hand_types() -> Result<()> {
+        let t = |rules: &Rules, hand| Ok::<_, anyhow::Error>(rules.parse_hand(hand)?.t.to_string());
+        let (standard, jokers) = (Rules::standard(), Rules::jokers());
+        assert_eq!(t(&standard, "32T3K")?, "One pair");
+        assert_eq!(t(&standard, "KTJJT")?, "Two pair");
+        assert_eq!(t(&jokers, "KTJJT")?, "Four of a kind");
+        assert_eq!(t(&jokers, "QQQJA")?, "Four of a kind");
+        assert_eq!(t(&jokers, "JJJJJ")?, "Five of a kind");
+        assert_eq!(t(&jokers, "2345J")?, "One pair");
+        assert_eq!(t(&standard, "23332")?, "Full house");
+        // jokers are the weakest card when the types are equal
+        assert!(jokers.parse_hand("JKKK2")? < jokers.parse_hand("QQQQ2")?);
+        assert!(standard.parse_hand("JKKK2")? > standard.parse_hand("TKKK2")?);
+        assert!(standard.parse_hand("2345").is_err());
+        assert!(standard.parse_hand("2345X").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_best_hand() -> Result<()> {
+        let jokers = Rules::jokers();
+        assert_eq!(jokers.best_hand("KTJJT"), "KTTTT");
+        assert_eq!(jokers.best_hand("2345J"), "23455");
+        assert_eq!(jokers.best_hand("JJJJJ"), "AAAAA");
+        assert_eq!(Rules::standard().best_hand("KTJJT"), "KTJJT");
+        let rules = Rules::new(LABELS, "J2", 6)?;
+        assert_eq!(rules.best_hand("JJ2345"), "555345");
+        Ok(())
+    }
+
+    #[test]
+    fn test_explain() -> Result<()> {
+        let input = aoc::read_input(DAY, Some("example2"))?;
+        let expected = " \
+ rank  hand   type             best      bid    winnings
+    1  32T3K  One pair         32T3K     765         765
+    2  KK677  Two pair         KK677      28          56
+    3  T55J5  Four of a kind   T5555     684        2052
+    4  QQQJA  Four of a kind   QQQQA     483        1932
+    5  KTJJT  Four of a kind   KTTTT     220        1100
+total 5905
+";
+        assert_eq!(explain(&input, &Rules::jokers())?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_variants() -> Result<()> {
+        // JJ2345 is four of a kind with both wild ranks, 333444 two triples
+        let input = "AAKKQQ 1\n333444 10\nJJ2345 100\n";
+        let rules = Rules::new(LABELS, "J2", 6)?;
+        assert_eq!(rules.parse_hand("JJ2345")?.t.groups(), [4, 1, 1]);
+        assert_eq!(total_winnings(input, &rules)?, 1 + 10 * 2 + 100 * 3);
+        // without wild cards, JJ2345 is only a pair
+        let rules = Rules::new(LABELS, "", 6)?;
+        assert_eq!(total_winnings(input, &rules)?, 100 + 2 + 10 * 3);
+        // reversed order, aces low
+        let rules = Rules::new("AKQJT98765432", "", 5)?;
+        assert!(rules.parse_hand("2AAAA")? > rules.parse_hand("A2222")?);
+        assert!(Rules::new(LABELS, "X", 5).is_err());
+        assert!(Rules::new("AA", "", 5).is_err());
+        assert!(Rules::new(LABELS, "", MAX_HAND + 1).is_err());
+        Ok(())
+    }
 }
@@ -1,15 +1,16 @@
 use std::collections::HashMap;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
+use aoc::Flags;
 use itertools::Itertools;
-use num::integer::lcm;
+use num::Integer;
 
 const DAY: u8 = 08;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Instruction {
-    Left,
-    Right,
+    Left = 0,
+    Right = 1,
 }
 
 impl TryFrom<char> for Instruction {
@@ -24,91 +25,319 @@ impl TryFrom<char> for Instruction {
     }
 }
 
-fn part_1(input: &str) -> Result<u32> {
+/// Nodes interned into dense indices, `edges[node][instruction as usize]` is the next node.
+#[derive(Debug, Clone, Default)]
+struct Network<'a> {
+    names: Vec<&'a str>,
+    index: HashMap<&'a str, u32>,
+    edges: Vec<[u32; 2]>,
+}
+
+impl<'a> Network<'a> {
+    fn intern(&mut self, name: &'a str) -> u32 {
+        *self.index.entry(name).or_insert_with(|| {
+            self.names.push(name);
+            self.edges.push([u32::MAX; 2]);
+            self.names.len() as u32 - 1
+        })
+    }
+
+    fn parse(lines: impl Iterator<Item = &'a str>) -> Result<Self> {
+        let mut network = Self::default();
+        for l in lines {
+            let Some((name, edges)) = l.split_once(" = ") else {
+                anyhow::bail!("Invalid node: {}", l);
+            };
+            let Some((left, right)) = edges
+                .strip_prefix('(')
+                .and_then(|e| e.strip_suffix(')'))
+                .and_then(|e| e.split_once(", "))
+            else {
+                anyhow::bail!("Invalid node: {}", l);
+            };
+            if [name, left, right].contains(&"") {
+                anyhow::bail!("Empty node name: {}", l);
+            }
+            let node = network.intern(name);
+            network.edges[node as usize] = [network.intern(left), network.intern(right)];
+        }
+        if let Some(node) = (0..network.names.len()).find(|&n| network.edges[n][0] == u32::MAX) {
+            anyhow::bail!("Node {} is never defined", network.names[node]);
+        }
+        Ok(network)
+    }
+
+    fn node(&self, name: &str) -> Option<u32> {
+        self.index.get(name).copied()
+    }
+
+    fn name(&self, node: u32) -> &'a str {
+        self.names[node as usize]
+    }
+
+    fn step(&self, node: u32, instruction: Instruction) -> u32 {
+        self.edges[node as usize][instruction as usize]
+    }
+
+    /// Nodes whose names end with `suffix`, in input order.
+    fn ending_with(&self, suffix: char) -> impl Iterator<Item = u32> + '_ {
+        (0..self.names.len() as u32).filter(move |&n| self.name(n).ends_with(suffix))
+    }
+
+    /// Graphviz graph of the network, with a cluster for the nodes reachable by each ghost.
+    fn to_dot(&self) -> String {
+        let mut owner = vec![None; self.names.len()];
+        for (ghost, start) in self.ending_with('A').enumerate() {
+            let mut stack = vec![start];
+            while let Some(node) = stack.pop() {
+                if owner[node as usize].is_none() {
+                    owner[node as usize] = Some(ghost);
+                    stack.extend(self.edges[node as usize]);
+                }
+            }
+        }
+        let mut dot = String::from("digraph network {\n");
+        let node_line = |node: u32| {
+            let name = self.name(node);
+            let color = if name.ends_with('A') {
+                " [style=filled, fillcolor=palegreen]"
+            } else if name.ends_with('Z') {
+                " [style=filled, fillcolor=salmon]"
+            } else {
+                ""
+            };
+            format!("\"{}\"{};\n", name, color)
+        };
+        for (ghost, start) in self.ending_with('A').enumerate() {
+            dot += &format!(
+                "    subgraph cluster_{} {{\n        label=\"{}\";\n",
+                ghost,
+                self.name(start)
+            );
+            for node in (0..self.names.len() as u32).filter(|&n| owner[n as usize] == Some(ghost)) {
+                dot += "        ";
+                dot += &node_line(node);
+            }
+            dot += "    }\n";
+        }
+        for node in (0..self.names.len() as u32).filter(|&n| owner[n as usize].is_none()) {
+            dot += "    ";
+            dot += &node_line(node);
+        }
+        for (node, &[left, right]) in self.edges.iter().enumerate() {
+            let name = self.names[node];
+            if left == right {
+                dot += &format!(
+                    "    \"{}\" -> \"{}\" [label=\"LR\"];\n",
+                    name,
+                    self.name(left)
+                );
+            } else {
+                dot += &format!(
+                    "    \"{}\" -> \"{}\" [label=\"L\"];\n",
+                    name,
+                    self.name(left)
+                );
+                dot += &format!(
+                    "    \"{}\" -> \"{}\" [label=\"R\"];\n",
+                    name,
+                    self.name(right)
+                );
+            }
+        }
+        dot += "}\n";
+        dot
+    }
+}
+
+fn parse(input: &str) -> Result<(Vec<Instruction>, Network<'_>)> {
     let mut lines = input.lines();
-    let mut instructions = lines
+    let instructions = lines
         .next()
-        .unwrap()
+        .unwrap_or_default()
         .chars()
-        .map(|c| Instruction::try_from(c).unwrap())
-        .cycle();
-    lines.next().unwrap();
-    let graph = lines
-        .map(|l| l.split(" = ").collect_tuple().unwrap())
-        .map(|(name, edges)| {
-            (
-                name,
-                edges[1..edges.len() - 1]
-                    .split(", ")
-                    .collect_tuple()
-                    .unwrap(),
-            )
-        })
-        .collect::<HashMap<_, (_, _)>>();
-    let mut curr = "AAA";
+        .map(Instruction::try_from)
+        .collect::<Result<Vec<_>>>()?;
+    if instructions.is_empty() {
+        anyhow::bail!("No instructions");
+    }
+    lines.next();
+    Ok((instructions, Network::parse(lines)?))
+}
+
+/// Steps from `start` to `goal`, following the instructions.
+///
+/// Fails if the walk comes back to a (node, instruction index) state it has already been in,
+/// since from then on it only goes around in circles.
+fn navigate(
+    network: &Network,
+    instructions: &[Instruction],
+    start: &str,
+    goal: &str,
+) -> Result<u64> {
+    let node = |name| {
+        network
+            .node(name)
+            .with_context(|| format!("No node named {}", name))
+    };
+    let (mut curr, goal_node) = (node(start)?, node(goal)?);
+    let mut seen = vec![false; network.names.len() * instructions.len()];
     let mut steps = 0;
-    while curr != "ZZZ" {
-        if instructions.next().unwrap() == Instruction::Left {
-            curr = graph[curr].0;
-        } else {
-            curr = graph[curr].1;
+    while curr != goal_node {
+        let i = (steps % instructions.len() as u64) as usize;
+        let state = curr as usize * instructions.len() + i;
+        if seen[state] {
+            anyhow::bail!("{} is never reached from {}", goal, start);
         }
+        seen[state] = true;
+        curr = network.step(curr, instructions[i]);
         steps += 1;
     }
     Ok(steps)
 }
 
-fn part_2(input: &str) -> Result<u64> {
-    let mut lines = input.lines();
-    let instructions = lines
-        .next()
-        .unwrap()
-        .chars()
-        .map(|c| Instruction::try_from(c).unwrap())
-        .cycle();
-    lines.next().unwrap();
-    let mut starts = vec![];
-    let graph = lines
-        .map(|l| l.split(" = ").collect_tuple().unwrap())
-        .map(|(name, edges)| {
-            (
-                name,
-                edges[1..edges.len() - 1]
-                    .split(", ")
-                    .collect_tuple()
-                    .unwrap(),
-            )
-        })
-        .inspect(|(name, _)| {
-            if name.ends_with('A') {
-                starts.push(*name);
-            }
-        })
-        .collect::<HashMap<_, (_, _)>>();
-    let mut steps = vec![];
-    for mut curr in starts {
-        let mut instructions = instructions.clone();
-        let mut step = 0;
-        while !curr.ends_with('Z') {
-            if instructions.next().unwrap() == Instruction::Left {
-                curr = graph[curr].0;
+fn part_1(input: &str) -> Result<u64> {
+    let (instructions, network) = parse(input)?;
+    navigate(&network, &instructions, "AAA", "ZZZ")
+}
+
+/// Steps `offset + k * period` for every `k >= 0`, or just `offset` if `period` is 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Hits {
+    offset: u64,
+    period: u64,
+}
+
+impl Hits {
+    /// Steps in both `self` and `other`, by the Chinese remainder theorem.
+    ///
+    /// Fails if the combined offset or period does not fit in a `u64`.
+    fn intersect(self, other: Self) -> Result<Option<Self>> {
+        let (a, b) = if self.period == 0 {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        if a.period == 0 {
+            let hit = if b.period == 0 {
+                a.offset == b.offset
             } else {
-                curr = graph[curr].1;
-            }
-            step += 1;
+                a.offset >= b.offset && (a.offset - b.offset) % b.period == 0
+            };
+            return Ok(hit.then_some(a));
+        }
+        let (p, q) = (a.period as i128, b.period as i128);
+        let gcd = p.extended_gcd(&q);
+        let diff = b.offset as i128 - a.offset as i128;
+        if diff % gcd.gcd != 0 {
+            return Ok(None);
         }
-        steps.push(step);
+        let period = u64::try_from(p / gcd.gcd * q).map_err(|_| {
+            anyhow::anyhow!("Combined period of {} and {} overflows", a.period, b.period)
+        })?;
+        // p * x = gcd (mod q), so p * x * diff / gcd = diff (mod q)
+        let m = q / gcd.gcd;
+        let k = (diff / gcd.gcd).rem_euclid(m) as u128 * gcd.x.rem_euclid(m) as u128 % m as u128;
+        let mut offset = (a.offset as u128 + a.period as u128 * k) % period as u128;
+        let min = a.offset.max(b.offset) as u128;
+        if offset < min {
+            offset += (min - offset).div_ceil(period as u128) * period as u128;
+        }
+        let offset = u64::try_from(offset).map_err(|_| {
+            anyhow::anyhow!("Combined offset of {} and {} overflows", a.offset, b.offset)
+        })?;
+        Ok(Some(Self { offset, period }))
     }
-    Ok(steps.into_iter().fold(1, lcm))
+}
+
+/// Every step at which the ghost starting at `start` stands on a `..Z` node.
+///
+/// The walk is a sequence of (node, instruction index) states, which eventually repeats.
+fn ghost_hits(network: &Network, instructions: &[Instruction], start: u32) -> Vec<Hits> {
+    let next = |(node, i): &mut (u32, usize)| {
+        *node = network.step(*node, instructions[*i]);
+        *i = (*i + 1) % instructions.len();
+    };
+    let cycle = aoc::cycle::find(&(start, 0), next);
+    let mut state = (start, 0);
+    let mut hits = vec![];
+    for s in 0..cycle.start + cycle.len {
+        if network.name(state.0).ends_with('Z') {
+            let period = if s < cycle.start { 0 } else { cycle.len };
+            hits.push(Hits {
+                offset: s as u64,
+                period: period as u64,
+            });
+        }
+        next(&mut state);
+    }
+    hits
+}
+
+fn part_2(input: &str) -> Result<u64> {
+    let (instructions, network) = parse(input)?;
+    let mut common = vec![Hits {
+        offset: 0,
+        period: 1,
+    }];
+    for start in network.ending_with('A') {
+        let hits = ghost_hits(&network, &instructions, start);
+        // every combination of the hits of the ghosts so far, so at worst the product
+        // of the number of `..Z` states on every ghost's walk (puzzle inputs have one each)
+        common = common
+            .iter()
+            .cartesian_product(&hits)
+            .filter_map(|(a, b)| a.intersect(*b).transpose())
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
+            .unique()
+            .collect();
+    }
+    match common.iter().map(|h| h.offset).min() {
+        Some(steps) => Ok(steps),
+        None => anyhow::bail!("The ghosts are never all on ..Z nodes at once"),
+    }
+}
+
+fn dot(input: &str) -> Result<()> {
+    print!("{}", parse(input)?.1.to_dot());
+    Ok(())
+}
+
+fn navigate_cmd(input: &str, start: &str, goal: &str) -> Result<()> {
+    let (instructions, network) = parse(input)?;
+    let steps = navigate(&network, &instructions, start, goal)?;
+    println!("{} -> {}: {} steps", start, goal, steps);
+    Ok(())
 }
 
 fn main() -> Result<()> {
-    aoc::solve_all(DAY, part_1, part_2)?;
+    const USAGE: &str =
+        "Usage: [dot [--input <suffix>] | navigate <start> <goal> [--input <suffix>]]";
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let Some(cmd) = args.first() else {
+        return aoc::solve_all(DAY, part_1, part_2);
+    };
+    let flags = Flags::parse(&args[1..], &["--input"], &[], USAGE)?;
+    let input = || aoc::read_input(DAY, flags.input());
+    match cmd.as_str() {
+        "dot" => {
+            flags.positional::<0>()?;
+            dot(&input()?)?
+        }
+        "navigate" => {
+            let [start, goal] = flags.positional()?;
+            navigate_cmd(&input()?, start, goal)?
+        }
+        _ => anyhow::bail!(USAGE),
+    }
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
+    use aoc::generate::Rng;
+
     use super::*;
 
     #[test]
@@ -124,4 +353,180 @@ mod tests {
         aoc::assert_output_matches_str(DAY, "example2", part_2, res)?;
         Ok(())
     }
+
+    #[test]
+    fn test_unaligned_cycles() -> Result<()> {
+        // 11A hits at 2 + 3k, 22A at 1 + 2k, so the lcm of the first hits (2) is wrong
+        let res = "5";
+        aoc::assert_output_matches_str(DAY, "example3", part_2, res)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_common_step() {
+        // 11A hits at every odd step, 22A at every even one
+        let input = "L\n\n11A = (11Z, 11Z)\n11Z = (11A, 11A)\n22A = (22B, 22B)\n\
+                     22B = (22Z, 22Z)\n22Z = (22B, 22B)\n";
+        assert!(part_2(input).is_err());
+    }
+
+    #[test]
+    fn test_navigate() -> Result<()> {
+        let input = aoc::read_input(DAY, Some("example2"))?;
+        let (instructions, network) = parse(&input)?;
+        assert_eq!(navigate(&network, &instructions, "11A", "11Z")?, 2);
+        assert_eq!(navigate(&network, &instructions, "22A", "22Z")?, 3);
+        assert_eq!(navigate(&network, &instructions, "22A", "22A")?, 0);
+        // XXX only leads to itself
+        let err = navigate(&network, &instructions, "XXX", "11Z").unwrap_err();
+        assert_eq!(err.to_string(), "11Z is never reached from XXX");
+        // ghost 22A never gets to the nodes of ghost 11A
+        assert!(navigate(&network, &instructions, "22A", "11B").is_err());
+        // no AAA at all
+        let err = part_1(&input).unwrap_err();
+        assert_eq!(err.to_string(), "No node named AAA");
+        Ok(())
+    }
+
+    #[test]
+    fn test_network() -> Result<()> {
+        let input = aoc::read_input(DAY, Some("example1"))?;
+        let (instructions, network) = parse(&input)?;
+        use Instruction::*;
+        assert_eq!(instructions, [Left, Left, Right]);
+        assert_eq!(network.names, ["AAA", "BBB", "ZZZ"]);
+        assert_eq!(network.edges, [[1, 1], [0, 2], [2, 2]]);
+        let bbb = network.node("BBB").unwrap();
+        assert_eq!(network.name(network.step(bbb, Right)), "ZZZ");
+        assert!(parse("L\n\nAAA = (BBB, AAA)\n").is_err());
+        assert!(parse("L\n\n = (AAA, AAA)\nAAA = (AAA, AAA)\n").is_err());
+        assert!(parse("L\n\nAAA = (, AAA)\n").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_dot() -> Result<()> {
+        let input = aoc::read_input(DAY, Some("example2"))?;
+        let dot = parse(&input)?.1.to_dot();
+        assert!(dot.contains("subgraph cluster_1 {\n        label=\"22A\";\n"));
+        assert!(dot.contains("    \"22B\" -> \"22C\" [label=\"LR\"];\n"));
+        assert!(dot.contains("    \"11A\" -> \"XXX\" [label=\"R\"];\n"));
+        // XXX is reachable by both ghosts, but only drawn once
+        assert_eq!(dot.matches("\"XXX\";").count(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_intersect() -> Result<()> {
+        let hits = |offset, period| Hits { offset, period };
+        assert_eq!(hits(2, 3).intersect(hits(1, 2))?, Some(hits(5, 6)));
+        assert_eq!(hits(1, 2).intersect(hits(2, 4))?, None);
+        assert_eq!(hits(4, 6).intersect(hits(10, 4))?, Some(hits(10, 12)));
+        // one-off hits before the cycle
+        assert_eq!(hits(7, 0).intersect(hits(1, 3))?, Some(hits(7, 0)));
+        assert_eq!(hits(7, 0).intersect(hits(8, 1))?, None);
+        assert_eq!(hits(7, 0).intersect(hits(7, 0))?, Some(hits(7, 0)));
+        // two primes just below 2^32, the combined period barely fits
+        let (p, q) = (4_294_967_291, 4_294_967_279);
+        assert_eq!(
+            hits(5, p).intersect(hits(7, q))?,
+            Some(hits(3_074_457_330_585_873_085, p * q))
+        );
+        assert!(hits(5, p).intersect(hits(7, 1 << 33)).is_err());
+        assert!(hits(u64::MAX - 1, 2).intersect(hits(u64::MAX, 3)).is_err());
+        Ok(())
+    }
+
+    /// Moves all the ghosts at once until they all stand on a `..Z` node,
+    /// or gives up after `limit` steps.
+    fn brute_force(input: &str, limit: u64) -> Option<u64> {
+        let (instructions, nodes) = input.split_once("\n\n").unwrap();
+        let graph = nodes
+            .lines()
+            .map(|l| (&l[0..3], (&l[7..10], &l[12..15])))
+            .collect::<HashMap<_, _>>();
+        let mut ghosts = graph
+            .keys()
+            .copied()
+            .filter(|n| n.ends_with('A'))
+            .collect_vec();
+        for (steps, instruction) in instructions.chars().cycle().enumerate() {
+            if ghosts.iter().all(|g| g.ends_with('Z')) {
+                return Some(steps as u64);
+            }
+            if steps as u64 == limit {
+                return None;
+            }
+            for g in ghosts.iter_mut() {
+                *g = if instruction == 'L' {
+                    graph[*g].0
+                } else {
+                    graph[*g].1
+                };
+            }
+        }
+        unreachable!()
+    }
+
+    /// Ghosts walking a lead-in before their ring, with any number of `..Z` nodes
+    /// anywhere on it (unlike the generated ones, where the least common multiple works).
+    fn unaligned_ghosts(seed: u64) -> String {
+        let mut rng = Rng::new(seed);
+        let instructions = (0..rng.range(1..6))
+            .map(|_| if rng.chance(0.5) { 'L' } else { 'R' })
+            .collect::<String>();
+        let mut count = 0;
+        let mut name = |last: char| {
+            count += 1;
+            let letter = |i: usize| (b'A' + i as u8) as char;
+            format!("{}{}{}", letter(count / 26), letter(count % 26), last)
+        };
+        let mut lines = vec![];
+        for _ in 0..rng.range(2..4) {
+            let lead_in = rng.range(0..5) as usize;
+            let ring = rng.range(1..8) as usize;
+            let mut path = vec![name('A')];
+            for _ in 1..lead_in + ring {
+                path.push(name(if rng.chance(0.4) { 'Z' } else { 'B' }));
+            }
+            for (i, node) in path.iter().enumerate() {
+                let next = &path[if i + 1 < path.len() { i + 1 } else { lead_in }];
+                lines.push(format!("{} = ({}, {})", node, next, next));
+            }
+        }
+        rng.shuffle(&mut lines);
+        format!("{}\n\n{}\n", instructions, lines.join("\n"))
+    }
+
+    #[test]
+    fn test_brute_force() -> Result<()> {
+        for seed in 0..30 {
+            let input = aoc::generate::generate(DAY, 1 + seed as usize * 2, seed)?;
+            assert_eq!(
+                Some(part_2(&input)?),
+                brute_force(&input, u64::MAX),
+                "{}",
+                input
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_brute_force_unaligned() {
+        let mut solvable = 0;
+        for seed in 0..300 {
+            let input = unaligned_ghosts(seed);
+            // past the lead-ins (at most 4 steps), the ghosts repeat after at most 7^3 steps
+            match (part_2(&input), brute_force(&input, 2000)) {
+                (Ok(steps), Some(expected)) => {
+                    assert_eq!(steps, expected, "{}", input);
+                    solvable += 1;
+                }
+                (Err(_), None) => {}
+                (res, expected) => panic!("{:?} instead of {:?}\n{}", res, expected, input),
+            }
+        }
+        assert!(solvable > 100, "only {} solvable inputs", solvable);
+    }
 }
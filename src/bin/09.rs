@@ -1,63 +1,133 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
+use aoc::Flags;
 use itertools::Itertools;
+use num::{BigRational, One, ToPrimitive, Zero};
 
 const DAY: u8 = 09;
 
-fn get_differences(seq: &[i64]) -> Vec<i64> {
-    seq.iter().tuple_windows().map(|(a, b)| b - a).collect()
+/// The lowest degree polynomial through a sequence, in Newton's forward difference form:
+/// `value(k) = sum of diffs[j] * binomial(k, j)`, with the first element at `k = 0`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Newton {
+    /// First entry of every row of differences, down to the constant one.
+    diffs: Vec<i64>,
 }
 
-fn part_1(input: &str) -> Result<i64> {
-    Ok(input
-        .lines()
-        .map(|l| {
-            l.split(' ')
-                .map(str::parse)
-                .map(Result::unwrap)
-                .collect_vec()
-        })
-        .map(|s| {
-            // ENHANCE: O(N) memory
-            let mut difs = vec![s];
-            difs.push(get_differences(difs.last().unwrap()));
-            while !difs.last().unwrap().iter().all_equal() {
-                difs.push(get_differences(difs.last().unwrap()));
+impl Newton {
+    /// Fails if no row of differences with at least two entries is constant,
+    /// as a single number says nothing about the sequence being a polynomial.
+    fn fit(seq: &[i64]) -> Result<Self> {
+        let mut row = seq.to_vec();
+        let mut diffs = vec![];
+        while row.len() >= 2 {
+            diffs.push(row[0]);
+            if row.iter().all_equal() {
+                return Ok(Self { diffs });
+            }
+            for i in 0..row.len() - 1 {
+                row[i] = row[i + 1]
+                    .checked_sub(row[i])
+                    .context("Differences overflow")?;
+            }
+            row.pop();
+        }
+        anyhow::bail!("The differences of {:?} never become constant", seq)
+    }
+
+    fn degree(&self) -> usize {
+        self.diffs.len() - 1
+    }
+
+    /// Value at `k`, which may also be before the start, far past the end, or a fraction.
+    fn value_at(&self, k: &BigRational) -> BigRational {
+        let mut value = BigRational::zero();
+        let mut binomial = BigRational::one();
+        for (j, &d) in self.diffs.iter().enumerate() {
+            value += &binomial * BigRational::from_integer(d.into());
+            binomial = binomial * (k - BigRational::from_integer(j.into()))
+                / BigRational::from_integer((j + 1).into());
+        }
+        value
+    }
+
+    /// Same as `value_at`, all in integers unless they overflow.
+    fn value_at_index(&self, k: i64) -> Result<i64> {
+        let integer = || {
+            let (mut value, mut binomial) = (0i128, 1i128);
+            for (j, &d) in self.diffs.iter().enumerate() {
+                value = value.checked_add(binomial.checked_mul(d as i128)?)?;
+                // the product of j + 1 consecutive integers is divisible by (j + 1)!
+                binomial = binomial.checked_mul(k as i128 - j as i128)? / (j as i128 + 1);
             }
-            difs.into_iter().map(|mut v| v.pop().unwrap()).sum::<i64>()
-        })
-        .sum())
+            value.try_into().ok()
+        };
+        if let Some(value) = integer() {
+            return Ok(value);
+        }
+        let value = self.value_at(&BigRational::from_integer(k.into()));
+        // integer polynomials stay integers on integer indices
+        value
+            .to_integer()
+            .to_i64()
+            .with_context(|| format!("Value at {} does not fit: {}", k, value))
+    }
+}
+
+fn parse_line(line: &str) -> Result<Vec<i64>> {
+    Ok(line
+        .split_ascii_whitespace()
+        .map(str::parse)
+        .collect::<Result<_, _>>()?)
+}
+
+fn extrapolate(line: &str, forward: bool) -> Result<i64> {
+    let seq = parse_line(line)?;
+    let k = if forward { seq.len() as i64 } else { -1 };
+    Newton::fit(&seq)?.value_at_index(k)
+}
+
+fn part_1(input: &str) -> Result<i64> {
+    aoc::par::sum_lines(input, |l| extrapolate(l, true))
 }
 
 fn part_2(input: &str) -> Result<i64> {
-    Ok(input
-        .lines()
-        .map(|l| {
-            l.split(' ')
-                .map(str::parse)
-                .map(Result::unwrap)
-                .collect_vec()
-        })
-        .map(|s| {
-            let mut difs = vec![s];
-            difs.push(get_differences(difs.last().unwrap()));
-            while !difs.last().unwrap().iter().all_equal() {
-                difs.push(get_differences(difs.last().unwrap()));
-            }
-            difs.into_iter()
-                .map(|v| v.first().unwrap().clone())
-                .rev()
-                .fold(0, |acc, x| x - acc)
-        })
-        .sum())
+    aoc::par::sum_lines(input, |l| extrapolate(l, false))
+}
+
+fn predict(args: &[String]) -> Result<()> {
+    const USAGE: &str = "Usage: predict <line> <k> [--input <suffix>]";
+    let flags = Flags::parse(args, &["--input"], &[], USAGE)?;
+    let [line, k] = flags.positional()?;
+    let line: usize = line.parse()?;
+    let k: BigRational = k.parse()?;
+    let input = aoc::read_input(DAY, flags.input())?;
+    let Some(seq) = line.checked_sub(1).and_then(|l| input.lines().nth(l)) else {
+        anyhow::bail!("No line {}", line);
+    };
+    let newton = Newton::fit(&parse_line(seq)?)?;
+    println!(
+        "degree {}, value at {}: {}",
+        newton.degree(),
+        k,
+        newton.value_at(&k)
+    );
+    Ok(())
 }
 
 fn main() -> Result<()> {
-    aoc::solve_all(DAY, part_1, part_2)?;
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    match args.first().map(String::as_str) {
+        Some("predict") => predict(&args[1..])?,
+        None => aoc::solve_all(DAY, part_1, part_2)?,
+        _ => anyhow::bail!("Usage: [predict <line> <k> [--input <suffix>]]"),
+    }
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
+    use num::BigInt;
+
     use super::*;
 
     #[test]
@@ -73,4 +143,33 @@ mod tests {
         aoc::assert_output_matches_str(DAY, "example2", part_2, res)?;
         Ok(())
     }
+
+    #[test]
+    fn test_fit() -> Result<()> {
+        assert_eq!(Newton::fit(&[0, 3, 6, 9, 12, 15])?.diffs, [0, 3]);
+        assert_eq!(Newton::fit(&[10, 13, 16, 21, 30, 45])?.degree(), 3);
+        assert_eq!(Newton::fit(&[7, 7])?.degree(), 0);
+        // 1 2 4 8 16 keeps doubling, so only the last single difference is constant
+        assert!(Newton::fit(&[1, 2, 4, 8, 16]).is_err());
+        assert!(Newton::fit(&[5]).is_err());
+        assert!(Newton::fit(&[]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_value_at() -> Result<()> {
+        let squares = Newton::fit(&[0, 1, 4, 9, 16])?;
+        for k in -20..20 {
+            assert_eq!(squares.value_at_index(k)?, k * k);
+        }
+        let big = BigRational::from_integer(BigInt::from(10).pow(15));
+        assert_eq!(
+            squares.value_at(&big),
+            BigRational::from_integer(BigInt::from(10).pow(30))
+        );
+        assert!(squares.value_at_index(1 << 40).is_err());
+        let half: BigRational = "1/2".parse()?;
+        assert_eq!(squares.value_at(&half), "1/4".parse()?);
+        Ok(())
+    }
 }
@@ -1,83 +1,144 @@
 use anyhow::Result;
+use aoc::Flags;
+use itertools::Itertools;
 
 const DAY: u8 = 11;
 
-fn part_1(input: &str) -> Result<usize> {
-    let height = input.lines().count();
-    let width = input.lines().next().unwrap().chars().count();
-    let mut galaxies = vec![];
-    let mut empty_cols = vec![true; width];
-    let mut empty_rows = vec![true; height];
-    input.lines().enumerate().for_each(|(y, line)| {
-        line.chars().enumerate().for_each(|(x, c)| {
-            if c == '#' {
-                galaxies.push((y, x));
-                empty_cols[x] = false;
-                empty_rows[y] = false;
+/// Galaxies of the image, numbered in reading order, at their expanded coordinates.
+#[derive(Debug, Clone)]
+struct Image {
+    galaxies: Vec<(usize, usize)>,
+}
+
+impl Image {
+    /// Every empty row and column becomes `factor` of them.
+    fn parse(input: &str, factor: usize) -> Result<Self> {
+        let width = input.lines().next().map_or(0, str::len);
+        let mut galaxies = vec![];
+        let mut empty_cols = vec![true; width];
+        let mut y = 0;
+        for line in input.lines() {
+            if line.len() != width {
+                anyhow::bail!("Rows have different widths");
             }
-        });
-    });
-    let mut cols_pref = vec![0; width + 1];
-    let mut rows_pref = vec![0; height + 1];
-    for i in 0..width {
-        cols_pref[i + 1] = cols_pref[i] + empty_cols[i] as usize;
-    }
-    for i in 0..height {
-        rows_pref[i + 1] = rows_pref[i] + empty_rows[i] as usize;
-    }
-    let mut sum = 0;
-    for s in 0..galaxies.len() {
-        let (sy, sx) = galaxies[s];
-        for e in s + 1..galaxies.len() {
-            let (ey, ex) = galaxies[e];
-            sum += ex.abs_diff(sx)
-                + (ey - sy)
-                + (cols_pref[sx.max(ex) + 1] - cols_pref[sx.min(ex)])
-                + (rows_pref[ey + 1] - rows_pref[sy]);
+            let before = galaxies.len();
+            for (x, c) in line.bytes().enumerate() {
+                match c {
+                    b'#' => {
+                        galaxies.push((y, x));
+                        empty_cols[x] = false;
+                    }
+                    b'.' => {}
+                    _ => anyhow::bail!("Invalid pixel: {}", c as char),
+                }
+            }
+            y += if galaxies.len() == before { factor } else { 1 };
+        }
+        let mut col = vec![0; width];
+        let mut x = 0;
+        for (c, &empty) in empty_cols.iter().enumerate() {
+            col[c] = x;
+            x += if empty { factor } else { 1 };
         }
+        for g in galaxies.iter_mut() {
+            g.1 = col[g.1];
+        }
+        Ok(Self { galaxies })
     }
-    Ok(sum)
-}
 
-fn part_2(input: &str) -> Result<usize> {
-    let height = input.lines().count();
-    let width = input.lines().next().unwrap().chars().count();
-    let mut galaxies = vec![];
-    let mut empty_cols = vec![true; width];
-    let mut empty_rows = vec![true; height];
-    input.lines().enumerate().for_each(|(y, line)| {
-        line.chars().enumerate().for_each(|(x, c)| {
-            if c == '#' {
-                galaxies.push((y, x));
-                empty_cols[x] = false;
-                empty_rows[y] = false;
-            }
-        });
-    });
-    let mut cols_pref = vec![0; width + 1];
-    let mut rows_pref = vec![0; height + 1];
-    for i in 0..width {
-        cols_pref[i + 1] = cols_pref[i] + empty_cols[i] as usize;
+    /// Distance between galaxies `a` and `b`, numbered from 1.
+    fn distance(&self, a: usize, b: usize) -> Result<usize> {
+        let galaxy = |n: usize| match n.checked_sub(1).and_then(|i| self.galaxies.get(i)) {
+            Some(&g) => Ok(g),
+            None => anyhow::bail!("No galaxy {}", n),
+        };
+        let ((ay, ax), (by, bx)) = (galaxy(a)?, galaxy(b)?);
+        Ok(ay.abs_diff(by) + ax.abs_diff(bx))
     }
-    for i in 0..height {
-        rows_pref[i + 1] = rows_pref[i] + empty_rows[i] as usize;
+
+    /// Sum of the distances between all the pairs of galaxies.
+    ///
+    /// The rows and columns add up separately, and in sorted order
+    /// the i-th coordinate is the larger one in exactly i pairs.
+    fn total_distance(&self) -> usize {
+        let axis_sum = |coords: Vec<usize>| {
+            let mut prefix = 0;
+            let mut sum = 0;
+            for (i, c) in coords.into_iter().sorted_unstable().enumerate() {
+                sum += c * i - prefix;
+                prefix += c;
+            }
+            sum
+        };
+        axis_sum(self.galaxies.iter().map(|g| g.0).collect())
+            + axis_sum(self.galaxies.iter().map(|g| g.1).collect())
     }
-    let mut sum = 0;
-    for s in 0..galaxies.len() {
-        let (sy, sx) = galaxies[s];
-        for e in s + 1..galaxies.len() {
-            let (ey, ex) = galaxies[e];
-            sum += ex.abs_diff(sx)
-                + (ey - sy)
-                + (cols_pref[sx.max(ex) + 1] - cols_pref[sx.min(ex)]) * 999_999
-                + (rows_pref[ey + 1] - rows_pref[sy]) * 999_999;
+
+    /// Galaxy numbers and distance of the pair farthest apart.
+    ///
+    /// In coordinates rotated by 45 degrees, `y + x` and `y - x`, the distance
+    /// is the larger of the two differences, so only the extremes matter.
+    fn farthest_pair(&self) -> Option<(usize, usize, usize)> {
+        if self.galaxies.len() < 2 {
+            return None;
         }
+        let rotated = |key: fn(&(usize, usize)) -> isize| {
+            let (min, max) = (0..self.galaxies.len())
+                .minmax_by_key(|&i| key(&self.galaxies[i]))
+                .into_option()?;
+            let dist = (key(&self.galaxies[max]) - key(&self.galaxies[min])) as usize;
+            Some((min.min(max) + 1, min.max(max) + 1, dist))
+        };
+        let sum = rotated(|&(y, x)| (y + x) as isize)?;
+        let diff = rotated(|&(y, x)| y as isize - x as isize)?;
+        Some(if sum.2 >= diff.2 { sum } else { diff })
     }
-    Ok(sum)
+}
+
+fn part_1(input: &str) -> Result<usize> {
+    Ok(Image::parse(input, 2)?.total_distance())
+}
+
+fn part_2(input: &str) -> Result<usize> {
+    Ok(Image::parse(input, 1_000_000)?.total_distance())
+}
+
+const USAGE: &str =
+    "Usage: [distance <a> <b> | farthest | total] [--factor <n>] [--input <suffix>]";
+
+/// Image from `[--factor <n>] [--input <suffix>]`, expanded twice by default.
+fn parse_image(flags: &Flags) -> Result<Image> {
+    Image::parse(
+        &aoc::read_input(DAY, flags.input())?,
+        flags.parsed_or("--factor", 2)?,
+    )
 }
 
 fn main() -> Result<()> {
-    aoc::solve_all(DAY, part_1, part_2)?;
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let Some(cmd) = args.first() else {
+        return aoc::solve_all(DAY, part_1, part_2);
+    };
+    let flags = Flags::parse(&args[1..], &["--factor", "--input"], &[], USAGE)?;
+    match cmd.as_str() {
+        "distance" => {
+            let [a, b] = flags.positional()?;
+            let (a, b) = (a.parse()?, b.parse()?);
+            println!("{} -> {}: {}", a, b, parse_image(&flags)?.distance(a, b)?);
+        }
+        "farthest" => {
+            flags.positional::<0>()?;
+            match parse_image(&flags)?.farthest_pair() {
+                Some((a, b, dist)) => println!("{} -> {}: {}", a, b, dist),
+                None => println!("Less than two galaxies"),
+            }
+        }
+        "total" => {
+            flags.positional::<0>()?;
+            println!("{}", parse_image(&flags)?.total_distance());
+        }
+        _ => anyhow::bail!(USAGE),
+    }
     Ok(())
 }
 
@@ -98,4 +159,78 @@ mod tests {
         aoc::assert_output_matches_str(DAY, "example2", part_2, res)?;
         Ok(())
     }
+
+    #[test]
+    fn test_factors() -> Result<()> {
+        let input = aoc::read_input(DAY, Some("example1"))?;
+        assert_eq!(Image::parse(&input, 10)?.total_distance(), 1030);
+        assert_eq!(Image::parse(&input, 100)?.total_distance(), 8410);
+        let image = Image::parse(&input, 2)?;
+        assert_eq!(image.distance(5, 9)?, 9);
+        assert_eq!(image.distance(1, 7)?, 15);
+        assert_eq!(image.distance(3, 6)?, 17);
+        assert_eq!(image.distance(8, 9)?, 5);
+        assert!(image.distance(0, 1).is_err());
+        assert!(image.distance(1, 10).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_farthest_pair() -> Result<()> {
+        for seed in 0..20 {
+            let input = aoc::generate::generate(DAY, 5 + seed as usize, seed)?;
+            let image = Image::parse(&input, 1 + seed as usize)?;
+            let n = image.galaxies.len();
+            let best = (1..=n)
+                .tuple_combinations()
+                .map(|(a, b)| image.distance(a, b).unwrap())
+                .max();
+            assert_eq!(image.farthest_pair().map(|p| p.2), best, "{}", input);
+            if let Some((a, b, dist)) = image.farthest_pair() {
+                assert_eq!(image.distance(a, b)?, dist);
+            }
+        }
+        Ok(())
+    }
+
+    /// Walks every row and column between every pair of galaxies.
+    fn brute_force(input: &str, factor: usize) -> usize {
+        let grid = input.lines().map(str::as_bytes).collect::<Vec<_>>();
+        let galaxies = (0..grid.len())
+            .flat_map(|y| (0..grid[0].len()).map(move |x| (y, x)))
+            .filter(|&(y, x)| grid[y][x] == b'#')
+            .collect::<Vec<_>>();
+        let row_len = |y: usize| {
+            if grid[y].contains(&b'#') {
+                1
+            } else {
+                factor
+            }
+        };
+        let col_len = |x: usize| {
+            if grid.iter().any(|row| row[x] == b'#') {
+                1
+            } else {
+                factor
+            }
+        };
+        let mut sum = 0;
+        for (i, &(sy, sx)) in galaxies.iter().enumerate() {
+            for &(ey, ex) in &galaxies[i + 1..] {
+                sum += (sy.min(ey)..sy.max(ey)).map(row_len).sum::<usize>();
+                sum += (sx.min(ex)..sx.max(ex)).map(col_len).sum::<usize>();
+            }
+        }
+        sum
+    }
+
+    #[test]
+    fn test_brute_force() -> Result<()> {
+        for seed in 0..30 {
+            let input = aoc::generate::generate(DAY, 5 + seed as usize, seed)?;
+            assert_eq!(part_1(&input)?, brute_force(&input, 2), "{}", input);
+            assert_eq!(part_2(&input)?, brute_force(&input, 1_000_000), "{}", input);
+        }
+        Ok(())
+    }
 }
@@ -1,98 +1,175 @@
-use std::{collections::BTreeMap, iter::repeat};
-
 use anyhow::Result;
+use aoc::Flags;
 use itertools::Itertools;
 
 const DAY: u8 = 12;
 
-fn part_1(input: &str) -> Result<u32> {
-    Ok(input
-        .lines()
-        .map(|l| {
-            let (chars, seq) = l.split_once(' ').unwrap();
-            let seq = seq
-                .split(',')
-                .map(str::parse::<u8>)
-                .map(Result::unwrap)
-                .collect_vec();
-            let mut max = 0;
-            let mut curr = vec![(0, 0)];
-            for b in chars.bytes() {
-                let mut new = vec![];
-                if b != b'.' {
-                    for &(idx, count) in &curr {
-                        if idx < seq.len() && count < seq[idx] {
-                            new.push((idx, count + 1));
-                        }
-                    }
-                }
-                if b != b'#' {
-                    for &(idx, count) in &curr {
-                        if count == 0 {
-                            new.push((idx, count))
-                        } else if count == seq[idx] {
-                            new.push((idx + 1, 0))
-                        }
-                    }
-                }
-                max = max.max(new.len());
-                curr = new;
-            }
-            curr.into_iter()
-                .filter(|(idx, count)| {
-                    *idx == seq.len() || (*idx == seq.len() - 1 && *count == seq[*idx])
-                })
-                .count() as u32
+/// A row of springs (`.`, `#` or `?`) and its damaged group sizes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Row {
+    springs: Vec<u8>,
+    groups: Vec<usize>,
+}
+
+impl Row {
+    fn parse(line: &str) -> Result<Self> {
+        let Some((springs, groups)) = line.split_once(' ') else {
+            anyhow::bail!("Invalid row: {}", line);
+        };
+        if let Some(b) = springs.bytes().find(|b| !b".#?".contains(b)) {
+            anyhow::bail!("Invalid spring: {}", b as char);
+        }
+        Ok(Self {
+            springs: springs.bytes().collect(),
+            groups: groups.split(',').map(str::parse).try_collect()?,
         })
-        .sum())
+    }
+
+    /// `factor` copies of the springs joined by `?`, and `factor` copies of the groups.
+    fn unfold(&self, factor: usize) -> Self {
+        Self {
+            springs: vec![self.springs.as_slice(); factor].join(&b'?'),
+            groups: self.groups.repeat(factor),
+        }
+    }
 }
 
-fn part_2(input: &str) -> Result<u64> {
-    Ok(input
-        .lines()
-        .map(|l| {
-            let (chars, seq) = l.split_once(' ').unwrap();
-            let seq = repeat(seq)
-                .take(5)
-                .flat_map(|s| s.split(','))
-                .map(str::parse::<u8>)
-                .map(Result::unwrap)
-                .collect_vec();
-            let mut curr = BTreeMap::from([((0, 0), 1)]);
-            let len = chars.bytes().len() * 5 + 4;
-            let mut bytes = chars.bytes().chain("?".bytes()).cycle();
-            for _ in 0..len {
-                let b = bytes.next().unwrap();
-                let mut new = BTreeMap::new();
-                if b != b'.' {
-                    for (&(idx, count), &amt) in &curr {
-                        if idx < seq.len() && count < seq[idx] {
-                            *new.entry((idx, count + 1)).or_insert(0) += amt;
-                        }
-                    }
+/// Counts the arrangements of every suffix of a row,
+/// which is enough to count, enumerate and pick arrangements.
+struct Arrangements<'a> {
+    row: &'a Row,
+    /// `ways[pos][g]`: arrangements of `groups[g..]` in `springs[pos..]`
+    ways: Vec<Vec<u128>>,
+}
+
+impl<'a> Arrangements<'a> {
+    fn new(row: &'a Row) -> Self {
+        let (springs, groups) = (&row.springs, &row.groups);
+        let n = springs.len();
+        // operational springs before every position
+        let mut dots = vec![0; n + 1];
+        for (i, &b) in springs.iter().enumerate() {
+            dots[i + 1] = dots[i] + (b == b'.') as usize;
+        }
+        let mut ways = vec![vec![0; groups.len() + 1]; n + 2];
+        ways[n][groups.len()] = 1;
+        ways[n + 1][groups.len()] = 1;
+        for pos in (0..n).rev() {
+            for g in 0..=groups.len() {
+                let mut res = 0;
+                if springs[pos] != b'#' {
+                    res += ways[pos + 1][g];
                 }
-                if b != b'#' {
-                    for (&(idx, count), &amt) in &curr {
-                        if count == 0 {
-                            *new.entry((idx, count)).or_insert(0) += amt;
-                        } else if count == seq[idx] {
-                            *new.entry((idx + 1, 0)).or_insert(0) += amt;
-                        }
+                if springs[pos] != b'.' && g < groups.len() {
+                    let end = pos + groups[g];
+                    if end <= n && dots[end] == dots[pos] && springs.get(end) != Some(&b'#') {
+                        res += ways[end + 1][g + 1];
                     }
                 }
-                curr = new;
+                ways[pos][g] = res;
             }
-            curr.into_iter()
-                .filter(|&((idx, count), _)| {
-                    idx == seq.len() || (idx == seq.len() - 1 && count == seq[idx])
-                })
-                .map(|(_, amt)| amt)
-                .sum::<u64>()
-        })
-        .sum())
+        }
+        Self { row, ways }
+    }
+
+    fn count(&self) -> u128 {
+        self.ways[0][0]
+    }
+
+    /// The `k`-th arrangement, ordered as if `.` < `#`.
+    fn nth(&self, mut k: u128) -> Option<String> {
+        if k >= self.count() {
+            return None;
+        }
+        let (springs, groups) = (&self.row.springs, &self.row.groups);
+        let mut res = Vec::with_capacity(springs.len());
+        let (mut pos, mut g) = (0, 0);
+        while pos < springs.len() {
+            if springs[pos] != b'#' {
+                let skip = self.ways[pos + 1][g];
+                if k < skip {
+                    res.push(b'.');
+                    pos += 1;
+                    continue;
+                }
+                k -= skip;
+            }
+            res.extend(std::iter::repeat_n(b'#', groups[g]));
+            pos += groups[g];
+            if pos < springs.len() {
+                res.push(b'.');
+                pos += 1;
+            }
+            g += 1;
+        }
+        Some(String::from_utf8(res).unwrap())
+    }
+}
+
+fn total_arrangements(input: &str, factor: usize) -> Result<u128> {
+    aoc::par::sum_lines(input, |l| {
+        Ok(Arrangements::new(&Row::parse(l)?.unfold(factor)).count())
+    })
+}
+
+fn part_1(input: &str) -> Result<u128> {
+    total_arrangements(input, 1)
+}
+
+fn part_2(input: &str) -> Result<u128> {
+    total_arrangements(input, 5)
+}
+
+/// `i * count / shown` (for `i < shown <= count`), without overflowing.
+fn sample_index(i: u128, shown: u128, count: u128) -> u128 {
+    count / shown * i + count % shown * i / shown
+}
+
+/// `arrangements <line> [--unfold <factor>] [--limit <n> | --sample <n>] [--input <suffix>]`
+///
+/// `--limit` prints the first arrangements, `--sample` evenly spaced ones.
+fn arrangements(args: &[String]) -> Result<()> {
+    const USAGE: &str = "Usage: arrangements <line> [--unfold <factor>] \
+                         [--limit <n> | --sample <n>] [--input <suffix>]";
+    let options = ["--unfold", "--limit", "--sample", "--input"];
+    let flags = Flags::parse(args, &options, &[], USAGE)?;
+    let [line] = flags.positional()?;
+    let line: usize = line.parse()?;
+    let factor = flags.parsed_or("--unfold", 1)?;
+    let (limit, sample) = match (flags.parsed("--limit")?, flags.parsed("--sample")?) {
+        (Some(_), Some(_)) => return flags.usage(),
+        (_, Some(n)) => (n, true),
+        (limit, None) => (limit.unwrap_or(10), false),
+    };
+    let input = aoc::read_input(DAY, flags.input())?;
+    let Some(row) = line.checked_sub(1).and_then(|l| input.lines().nth(l)) else {
+        anyhow::bail!("No line {}", line);
+    };
+    let row = Row::parse(row)?.unfold(factor);
+    let arrangements = Arrangements::new(&row);
+    let count = arrangements.count();
+    println!(
+        "{} ({} arrangements)",
+        String::from_utf8_lossy(&row.springs),
+        count
+    );
+    let shown = count.min(limit);
+    for i in 0..shown {
+        let k = if sample {
+            sample_index(i, shown, count)
+        } else {
+            i
+        };
+        println!("{}", arrangements.nth(k).unwrap());
+    }
+    Ok(())
 }
 
 fn main() -> Result<()> {
+    let args = std::env::args().skip(1).collect_vec();
+    if args.first().is_some_and(|a| a == "arrangements") {
+        return arrangements(&args[1..]);
+    }
     aoc::solve_all(DAY, part_1, part_2)?;
     Ok(())
 }
@@ -114,4 +191,52 @@ mod tests {
         aoc::assert_output_matches_str(DAY, "example2", part_2, res)?;
         Ok(())
     }
+
+    #[test]
+    fn test_enumeration() -> Result<()> {
+        let input = aoc::read_input(DAY, Some("example1"))?;
+        for (line, unfolded) in input.lines().zip([1, 16384, 1, 16, 2500, 506250]) {
+            let row = Row::parse(line)?;
+            assert_eq!(Arrangements::new(&row.unfold(5)).count(), unfolded);
+            let arrangements = Arrangements::new(&row);
+            let all = (0..arrangements.count())
+                .map(|k| arrangements.nth(k).unwrap())
+                .collect_vec();
+            // `#` sorts before `.` in ASCII
+            assert!(all.iter().tuple_windows().all(|(a, b)| a > b));
+            for a in &all {
+                assert!(a
+                    .bytes()
+                    .zip(&row.springs)
+                    .all(|(b, &s)| s == b'?' || s == b));
+                let groups = a.split('.').filter(|g| !g.is_empty()).map(str::len);
+                assert!(groups.eq(row.groups.iter().copied()), "{line}: {a}");
+            }
+            assert_eq!(arrangements.nth(arrangements.count()), None);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_large_unfold() -> Result<()> {
+        // 40 separate springs out of 119 unknown ones: C(119 - 40 + 1, 40)
+        let row = Row::parse("?? 1")?.unfold(40);
+        let expected = (0..40u128).fold(1, |acc, i| acc * (80 - i) / (i + 1));
+        assert_eq!(Arrangements::new(&row).count(), expected);
+        assert!(expected > u64::MAX as u128);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sample_index() {
+        for (shown, count) in [(1, 1), (3, 10), (10, 10), (7, 1000)] {
+            for i in 0..shown {
+                assert_eq!(sample_index(i, shown, count), i * count / shown);
+            }
+        }
+        let count = u128::MAX - 1;
+        assert_eq!(sample_index(0, 10, count), 0);
+        assert_eq!(sample_index(5, 10, count), count / 2);
+        assert!(sample_index(9, 10, count) < count);
+    }
 }
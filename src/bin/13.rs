@@ -1,4 +1,5 @@
 use anyhow::Result;
+use aoc::Flags;
 use itertools::Itertools;
 
 const DAY: u8 = 13;
@@ -6,36 +7,66 @@ const DAY: u8 = 13;
 /// (y, x)
 type Vec2 = (usize, usize);
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mirror {
+    /// Below this many rows.
+    Horizontal(usize),
+    /// Right of this many columns.
+    Vertical(usize),
+}
+
+impl Mirror {
+    fn summary(self) -> usize {
+        match self {
+            Self::Horizontal(rows) => rows * 100,
+            Self::Vertical(cols) => cols,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Reflection {
+    mirror: Mirror,
+    /// Cells above or left of the mirror that differ from their mirror image.
+    /// Fixing either one of the two cells works.
+    smudges: Vec<Vec2>,
+}
+
 /// get_coord((search_dir, find_dir)) -> index
-fn find_pattern(
+///
+/// Returns every line with exactly `wanted_diff` differences,
+/// with the (search_dir, find_dir) coordinates of the differences.
+fn find_patterns(
     block: &[u8],
     dim: Vec2,
     get_coord: impl Fn(Vec2) -> usize,
-    wanted_diff: u32,
-) -> Option<usize> {
+    wanted_diff: usize,
+) -> Vec<(usize, Vec<Vec2>)> {
     let diff = |first, second| {
         (0..dim.1)
-            .map(|i| (block[get_coord((first, i))] != block[get_coord((second, i))]) as u32)
-            .sum::<u32>()
+            .filter(|&i| block[get_coord((first, i))] != block[get_coord((second, i))])
+            .map(|i| (first, i))
+            .collect::<Vec<_>>()
     };
     let mut candidates = vec![];
     for first in 0..dim.0 - 1 {
-        if diff(first, first + 1) <= wanted_diff {
+        if diff(first, first + 1).len() <= wanted_diff {
             candidates.push(first);
         }
     }
+    let mut found = vec![];
     for candidate in candidates {
         let mut first = candidate;
         let mut second = candidate + 1;
-        let mut d = 0;
+        let mut diffs = vec![];
         loop {
-            d += diff(first, second);
-            if d > wanted_diff {
+            diffs.extend(diff(first, second));
+            if diffs.len() > wanted_diff {
                 break;
             }
             if first == 0 || second == dim.0 - 1 {
-                if d == wanted_diff {
-                    return Some(candidate + 1);
+                if diffs.len() == wanted_diff {
+                    found.push((candidate + 1, diffs));
                 }
                 break;
             }
@@ -43,79 +74,151 @@ fn find_pattern(
             second += 1;
         }
     }
-    None
+    found
 }
 
-fn part_1(input: &str) -> Result<usize> {
-    Ok(input
-        .lines()
-        .batching(|it| {
-            let mut block = vec![];
-            let mut height = 0;
-            let mut width = 0;
-            while let Some(line) = it.next() {
-                if line.is_empty() {
-                    break;
+#[derive(Debug, Clone)]
+struct Pattern {
+    cells: Vec<u8>,
+    dim: Vec2,
+}
+
+impl Pattern {
+    fn parse_all(input: &str) -> Vec<Self> {
+        input
+            .lines()
+            .batching(|it| {
+                let mut cells = vec![];
+                let mut height = 0;
+                let mut width = 0;
+                for line in it.by_ref() {
+                    if line.is_empty() {
+                        break;
+                    }
+                    cells.extend(line.bytes());
+                    width = line.len();
+                    height += 1;
+                }
+                if cells.is_empty() {
+                    return None;
+                }
+                Some(Self {
+                    cells,
+                    dim: (height, width),
+                })
+            })
+            .collect()
+    }
+
+    /// All reflections with exactly `smudges` smudges, horizontal ones first.
+    fn reflections(&self, smudges: usize) -> Vec<Reflection> {
+        let dim = self.dim;
+        let horizontal = find_patterns(&self.cells, dim, |(ser, fnd)| ser * dim.1 + fnd, smudges)
+            .into_iter()
+            .map(|(rows, diffs)| Reflection {
+                mirror: Mirror::Horizontal(rows),
+                smudges: diffs,
+            });
+        let vertical = find_patterns(
+            &self.cells,
+            (dim.1, dim.0),
+            |(ser, fnd)| fnd * dim.1 + ser,
+            smudges,
+        )
+        .into_iter()
+        .map(|(cols, diffs)| Reflection {
+            mirror: Mirror::Vertical(cols),
+            smudges: diffs.into_iter().map(|(x, y)| (y, x)).collect(),
+        });
+        horizontal.chain(vertical).collect()
+    }
+
+    /// The pattern with `><` above a vertical mirror, `v^` next to a horizontal one,
+    /// and the smudges highlighted.
+    fn render(&self, reflection: &Reflection) -> String {
+        const HIGHLIGHT: &str = "\x1b[1;33m";
+        const RESET: &str = "\x1b[0m";
+        let mut out = String::new();
+        if let Mirror::Vertical(cols) = reflection.mirror {
+            out += &format!("  {}><\n", " ".repeat(cols - 1));
+        }
+        for (y, row) in self.cells.chunks(self.dim.1).enumerate() {
+            out += match reflection.mirror {
+                Mirror::Horizontal(rows) if y + 1 == rows => "v ",
+                Mirror::Horizontal(rows) if y == rows => "^ ",
+                _ => "  ",
+            };
+            for (x, &c) in row.iter().enumerate() {
+                if reflection.smudges.contains(&(y, x)) {
+                    out.push_str(HIGHLIGHT);
+                    out.push(c as char);
+                    out.push_str(RESET);
+                } else {
+                    out.push(c as char);
                 }
-                block.extend(line.bytes());
-                width = line.len();
-                height += 1;
-            }
-            if block.is_empty() {
-                return None;
-            }
-            Some((block, (height, width)))
-        })
-        .map(|(block, dim)| {
-            if let Some(horizontal) = find_pattern(&block, dim, |(ser, fnd)| ser * dim.1 + fnd, 0) {
-                return horizontal * 100;
-            }
-            if let Some(vertical) =
-                find_pattern(&block, (dim.1, dim.0), |(ser, fnd)| fnd * dim.1 + ser, 0)
-            {
-                return vertical;
             }
-            unreachable!()
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// Sum of the summaries of the first reflection of every pattern.
+fn summarize(input: &str, smudges: usize) -> Result<usize> {
+    Pattern::parse_all(input)
+        .iter()
+        .enumerate()
+        .map(|(i, p)| match p.reflections(smudges).first() {
+            Some(reflection) => Ok(reflection.mirror.summary()),
+            None => anyhow::bail!(
+                "Pattern {} has no reflection with {} smudges",
+                i + 1,
+                smudges
+            ),
         })
-        .sum())
+        .sum()
+}
+
+fn part_1(input: &str) -> Result<usize> {
+    summarize(input, 0)
 }
 
 fn part_2(input: &str) -> Result<usize> {
-    Ok(input
-        .lines()
-        .batching(|it| {
-            let mut block = vec![];
-            let mut height = 0;
-            let mut width = 0;
-            while let Some(line) = it.next() {
-                if line.is_empty() {
-                    break;
-                }
-                block.extend(line.bytes());
-                width = line.len();
-                height += 1;
-            }
-            if block.is_empty() {
-                return None;
-            }
-            Some((block, (height, width)))
-        })
-        .map(|(block, dim)| {
-            if let Some(horizontal) = find_pattern(&block, dim, |(ser, fnd)| ser * dim.1 + fnd, 1) {
-                return horizontal * 100;
-            }
-            if let Some(vertical) =
-                find_pattern(&block, (dim.1, dim.0), |(ser, fnd)| fnd * dim.1 + ser, 1)
-            {
-                return vertical;
-            }
-            unreachable!()
-        })
-        .sum())
+    summarize(input, 1)
+}
+
+fn show(args: &[String]) -> Result<()> {
+    const USAGE: &str = "Usage: show [--smudges <n>] [--input <suffix>]";
+    let flags = Flags::parse(args, &["--smudges", "--input"], &[], USAGE)?;
+    flags.positional::<0>()?;
+    let smudges = flags.parsed_or("--smudges", 0)?;
+    let input = aoc::read_input(DAY, flags.input())?;
+    for (i, pattern) in Pattern::parse_all(&input).iter().enumerate() {
+        let reflections = pattern.reflections(smudges);
+        if reflections.is_empty() {
+            println!("Pattern {}: no reflection\n", i + 1);
+        }
+        for reflection in reflections {
+            println!(
+                "Pattern {}: {:?}, smudges at {:?}",
+                i + 1,
+                reflection.mirror,
+                reflection.smudges
+            );
+            println!("{}", pattern.render(&reflection));
+        }
+    }
+    println!("Summary: {}", summarize(&input, smudges)?);
+    Ok(())
 }
 
 fn main() -> Result<()> {
-    aoc::solve_all(DAY, part_1, part_2)?;
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    match args.first().map(String::as_str) {
+        Some("show") => show(&args[1..])?,
+        None => aoc::solve_all(DAY, part_1, part_2)?,
+        _ => anyhow::bail!("Usage: [show [--smudges <n>] [--input <suffix>]]"),
+    }
     Ok(())
 }
 
@@ -136,4 +239,113 @@ mod tests {
         aoc::assert_output_matches_str(DAY, "example2", part_2, res)?;
         Ok(())
     }
+
+    #[test]
+    fn test_smudges() -> Result<()> {
+        let patterns = Pattern::parse_all(&aoc::read_input(DAY, Some("example1"))?);
+        let reflection = |p: usize, smudges| patterns[p].reflections(smudges).remove(0);
+        assert_eq!(
+            reflection(0, 0),
+            Reflection {
+                mirror: Mirror::Vertical(5),
+                smudges: vec![]
+            }
+        );
+        assert_eq!(reflection(1, 0).mirror, Mirror::Horizontal(4));
+        assert_eq!(
+            reflection(0, 1),
+            Reflection {
+                mirror: Mirror::Horizontal(3),
+                smudges: vec![(0, 0)]
+            }
+        );
+        // or its mirror image (1, 4)
+        assert_eq!(
+            reflection(1, 1),
+            Reflection {
+                mirror: Mirror::Horizontal(1),
+                smudges: vec![(0, 4)]
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_all_reflections() -> Result<()> {
+        // symmetric left to right, and around every line once two smudges are allowed
+        let pattern = &Pattern::parse_all("#..#\n....\n#..#\n")[0];
+        let mirrors = |smudges| {
+            pattern
+                .reflections(smudges)
+                .into_iter()
+                .map(|r| r.mirror)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(mirrors(0), [Mirror::Vertical(2)]);
+        assert_eq!(mirrors(1), []);
+        assert_eq!(
+            mirrors(2),
+            [
+                Mirror::Horizontal(1),
+                Mirror::Horizontal(2),
+                Mirror::Vertical(1),
+                Mirror::Vertical(3)
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_reflection() {
+        let reflecting =
+            "#...##..#\n#....#..#\n..##..###\n#####.##.\n#####.##.\n..##..###\n#....#..#\n";
+        assert_eq!(summarize(reflecting, 0).unwrap(), 400);
+        let input = format!("{}\n#.#\n.#.\n", reflecting);
+        let err = summarize(&input, 0).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Pattern 2 has no reflection with 0 smudges"
+        );
+    }
+
+    #[test]
+    fn test_render() -> Result<()> {
+        let patterns = Pattern::parse_all(&aoc::read_input(DAY, Some("example1"))?);
+        let expected = "      ><
+  #.##..##.
+  ..#.##.#.
+  ##......#
+  ##......#
+  ..#.##.#.
+  ..##..##.
+  #.#.##.#.
+";
+        assert_eq!(patterns[0].render(&patterns[0].reflections(0)[0]), expected);
+        let expected = "  \x1b[1;33m#\x1b[0m.##..##.
+  ..#.##.#.
+v ##......#
+^ ##......#
+  ..#.##.#.
+  ..##..##.
+  #.#.##.#.
+";
+        assert_eq!(patterns[0].render(&patterns[0].reflections(1)[0]), expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_generated() -> Result<()> {
+        // every generated pattern has exactly one line with each smudge count
+        for seed in 0..10 {
+            let input = aoc::generate::generate(DAY, 10, seed)?;
+            for pattern in Pattern::parse_all(&input) {
+                let clean = pattern.reflections(0);
+                let smudged = pattern.reflections(1);
+                assert_eq!((clean.len(), smudged.len()), (1, 1));
+                assert_ne!(clean[0].mirror, smudged[0].mirror);
+                assert_eq!(smudged[0].smudges.len(), 1);
+            }
+        }
+        Ok(())
+    }
 }
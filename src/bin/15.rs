@@ -1,6 +1,12 @@
-use std::collections::BTreeMap;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+    io::BufRead,
+    marker::PhantomData,
+};
 
 use anyhow::Result;
+use aoc::Flags;
 
 const DAY: u8 = 15;
 
@@ -12,60 +18,314 @@ fn hash(part: &str) -> usize {
     })
 }
 
-fn part_1(input: &str) -> Result<usize> {
-    Ok(input.lines().flat_map(|l| l.split(',')).map(hash).sum())
+trait LabelHasher {
+    const NAME: &'static str;
+
+    fn hash(label: &str) -> u64;
+
+    /// Box of `label` when there are `buckets` boxes.
+    fn bucket_of(label: &str, buckets: usize) -> usize {
+        (Self::hash(label) % buckets as u64) as usize
+    }
+}
+
+/// The puzzle's HASH algorithm.
+struct AocHash;
+
+impl LabelHasher for AocHash {
+    const NAME: &'static str = "HASH";
+
+    fn hash(label: &str) -> u64 {
+        hash(label) as u64
+    }
+}
+
+struct Fnv1a;
+
+impl LabelHasher for Fnv1a {
+    const NAME: &'static str = "FNV-1a";
+
+    fn hash(label: &str) -> u64 {
+        label.bytes().fold(0xcbf2_9ce4_8422_2325, |acc, b| {
+            (acc ^ b as u64).wrapping_mul(0x0100_0000_01b3)
+        })
+    }
 }
 
-#[derive(Debug)]
+struct Djb2;
+
+impl LabelHasher for Djb2 {
+    const NAME: &'static str = "djb2";
+
+    fn hash(label: &str) -> u64 {
+        label.bytes().fold(5381, |acc: u64, b| {
+            acc.wrapping_mul(33).wrapping_add(b as u64)
+        })
+    }
+}
+
+/// Calls `f` with every step of the initialization sequence, without reading it all at once.
+fn for_each_step(reader: impl BufRead, mut f: impl FnMut(&str) -> Result<()>) -> Result<()> {
+    aoc::for_each_record(reader, b',', |record| {
+        record
+            .lines()
+            .filter(|s| !s.is_empty())
+            .try_for_each(&mut f)
+    })
+}
+
+fn part_1(input: &mut dyn BufRead) -> Result<usize> {
+    let mut sum = 0;
+    for_each_step(input, |step| {
+        sum += hash(step);
+        Ok(())
+    })?;
+    Ok(sum)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum CmdType {
     Remove,
     Add(usize),
 }
 
-fn parse_cmd(s: &str) -> (&str, CmdType) {
-    if s.ends_with('-') {
-        (&s[..s.len() - 1], CmdType::Remove)
-    } else {
-        let (label, num) = s.split_once('=').unwrap();
-        (label, CmdType::Add(num.parse().unwrap()))
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Step<'a> {
+    label: &'a str,
+    cmd: CmdType,
+}
+
+impl<'a> Step<'a> {
+    fn parse(s: &'a str) -> Result<Self> {
+        if let Some(label) = s.strip_suffix('-') {
+            return Ok(Self {
+                label,
+                cmd: CmdType::Remove,
+            });
+        }
+        let Some((label, num)) = s.split_once('=') else {
+            anyhow::bail!("Invalid step: {}", s);
+        };
+        Ok(Self {
+            label,
+            cmd: CmdType::Add(num.parse()?),
+        })
+    }
+}
+
+/// The boxes and lenses of the HASHMAP procedure.
+///
+/// Only the lenses currently in a box are kept, not the steps that put them there.
+struct LensLibrary<H: LabelHasher = AocHash> {
+    /// lens labels of every box, by insertion order
+    boxes: Vec<BTreeMap<usize, Box<str>>>,
+    /// (insertion order, focal length) of every lens in a box
+    lenses: HashMap<Box<str>, (usize, usize)>,
+    next_order: usize,
+    hasher: PhantomData<H>,
+}
+
+impl LensLibrary {
+    fn new() -> Self {
+        Self::with_buckets(256)
     }
 }
 
-fn part_2(input: &str) -> Result<usize> {
-    const EMPTY: BTreeMap<usize, &str> = BTreeMap::new();
-    let mut boxes = [EMPTY; 256];
-    let mut label_to_data = BTreeMap::new();
-    for (i, part) in input.lines().flat_map(|l| l.split(',')).enumerate() {
-        let (label, cmd) = parse_cmd(part);
-        let h = hash(label);
-        match cmd {
+impl<H: LabelHasher> LensLibrary<H> {
+    fn with_buckets(buckets: usize) -> Self {
+        Self {
+            boxes: vec![BTreeMap::new(); buckets],
+            lenses: HashMap::new(),
+            next_order: 0,
+            hasher: PhantomData,
+        }
+    }
+
+    fn apply(&mut self, step: Step) {
+        let h = H::bucket_of(step.label, self.boxes.len());
+        match step.cmd {
             CmdType::Remove => {
-                if let Some((order, _)) = label_to_data.remove(label) {
-                    boxes[h].remove(&order);
+                if let Some((order, _)) = self.lenses.remove(step.label) {
+                    self.boxes[h].remove(&order);
                 }
             }
             CmdType::Add(flen) => {
-                if let Some((_, old_flen)) = label_to_data.get_mut(label) {
+                if let Some((_, old_flen)) = self.lenses.get_mut(step.label) {
                     *old_flen = flen;
                 } else {
-                    label_to_data.insert(label, (i, flen));
-                    boxes[h].insert(i, label);
+                    self.lenses
+                        .insert(step.label.into(), (self.next_order, flen));
+                    self.boxes[h].insert(self.next_order, step.label.into());
+                    self.next_order += 1;
                 }
             }
         }
     }
-    let mut total = 0;
-    for (i, b) in boxes.into_iter().enumerate() {
-        for (order, &part) in b.values().enumerate() {
-            let flen = label_to_data[part].1;
-            total += (i + 1) * (order + 1) * flen;
+
+    /// (label, focal length) of the lenses in a box, front to back
+    fn lenses(&self, box_idx: usize) -> impl Iterator<Item = (&str, usize)> {
+        self.boxes[box_idx]
+            .values()
+            .map(|label| (&**label, self.lenses[label].1))
+    }
+
+    fn focusing_power(&self) -> usize {
+        let mut total = 0;
+        for i in 0..self.boxes.len() {
+            for (order, (_, flen)) in self.lenses(i).enumerate() {
+                total += (i + 1) * (order + 1) * flen;
+            }
+        }
+        total
+    }
+}
+
+/// Non-empty boxes, formatted like the puzzle walkthrough.
+impl<H: LabelHasher> Display for LensLibrary<H> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for i in 0..self.boxes.len() {
+            if self.boxes[i].is_empty() {
+                continue;
+            }
+            write!(f, "Box {}:", i)?;
+            for (label, flen) in self.lenses(i) {
+                write!(f, " [{} {}]", label, flen)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+fn part_2(input: &mut dyn BufRead) -> Result<usize> {
+    let mut library = LensLibrary::new();
+    for_each_step(input, |step| {
+        library.apply(Step::parse(step)?);
+        Ok(())
+    })?;
+    Ok(library.focusing_power())
+}
+
+/// The boxes after every step.
+fn trace(input: &str) -> Result<String> {
+    let mut library = LensLibrary::new();
+    let mut out = String::new();
+    for part in input.lines().flat_map(|l| l.split(',')) {
+        library.apply(Step::parse(part)?);
+        out.push_str(&format!("After \"{}\":\n{}\n", part, library));
+    }
+    Ok(out)
+}
+
+/// How evenly a hash spreads the distinct labels of a step sequence over the boxes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct BucketStats {
+    name: &'static str,
+    buckets: usize,
+    labels: usize,
+    used: usize,
+    max_load: usize,
+    /// pairs of distinct labels sharing a box
+    collisions: usize,
+    /// box load -> number of boxes with that load
+    histogram: BTreeMap<usize, usize>,
+}
+
+impl BucketStats {
+    fn new<H: LabelHasher>(reader: impl BufRead, buckets: usize) -> Result<Self> {
+        let mut labels = BTreeMap::<Box<str>, usize>::new();
+        for_each_step(reader, |step| {
+            let label = Step::parse(step)?.label;
+            if !labels.contains_key(label) {
+                labels.insert(label.into(), H::bucket_of(label, buckets));
+            }
+            Ok(())
+        })?;
+        let mut loads = vec![0; buckets];
+        labels.values().for_each(|&b| loads[b] += 1);
+        let mut histogram = BTreeMap::new();
+        loads
+            .iter()
+            .for_each(|&l| *histogram.entry(l).or_insert(0) += 1);
+        Ok(Self {
+            name: H::NAME,
+            buckets,
+            labels: labels.len(),
+            used: loads.iter().filter(|&&l| l > 0).count(),
+            max_load: loads.iter().copied().max().unwrap_or(0),
+            collisions: loads.iter().map(|l| l * l.saturating_sub(1) / 2).sum(),
+            histogram,
+        })
+    }
+}
+
+impl Display for BucketStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // for a perfectly uniform hash
+        let expected =
+            (self.labels * self.labels.saturating_sub(1)) as f64 / 2.0 / self.buckets as f64;
+        writeln!(f, "{}:", self.name)?;
+        writeln!(
+            f,
+            "  {} labels in {}/{} boxes, max load {}",
+            self.labels, self.used, self.buckets, self.max_load
+        )?;
+        writeln!(
+            f,
+            "  {} colliding pairs (uniform hash: {:.1})",
+            self.collisions, expected
+        )?;
+        write!(f, "  load distribution:")?;
+        for (load, count) in &self.histogram {
+            write!(f, " {}x{}", count, load)?;
         }
+        writeln!(f)
     }
-    Ok(total)
+}
+
+/// `trace [--input <suffix>]`
+fn trace_cmd(args: &[String]) -> Result<()> {
+    let flags = Flags::parse(args, &["--input"], &[], "Usage: trace [--input <suffix>]")?;
+    flags.positional::<0>()?;
+    print!("{}", trace(&aoc::read_input(DAY, flags.input())?)?);
+    Ok(())
+}
+
+/// `stats [--buckets <n>] [--input <suffix>]`
+fn stats(args: &[String]) -> Result<()> {
+    const USAGE: &str = "Usage: stats [--buckets <n>] [--input <suffix>]";
+    let flags = Flags::parse(args, &["--buckets", "--input"], &[], USAGE)?;
+    flags.positional::<0>()?;
+    let buckets = flags.parsed_or("--buckets", 256)?;
+    let suffix = flags.input();
+    if buckets == 0 {
+        anyhow::bail!("Need at least one box");
+    }
+    print!(
+        "{}",
+        BucketStats::new::<AocHash>(aoc::open_input(DAY, suffix)?, buckets)?
+    );
+    print!(
+        "{}",
+        BucketStats::new::<Fnv1a>(aoc::open_input(DAY, suffix)?, buckets)?
+    );
+    print!(
+        "{}",
+        BucketStats::new::<Djb2>(aoc::open_input(DAY, suffix)?, buckets)?
+    );
+    Ok(())
 }
 
 fn main() -> Result<()> {
-    aoc::solve_all(DAY, part_1, part_2)?;
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        ["trace", ..] => trace_cmd(&args[1..])?,
+        ["stats", ..] => stats(&args[1..])?,
+        [] => aoc::solve_all_streaming(DAY, part_1, part_2)?,
+        _ => anyhow::bail!(
+            "Usage: [trace [--input <suffix>] | stats [--buckets <n>] [--input <suffix>]]"
+        ),
+    }
     Ok(())
 }
 
@@ -76,14 +336,106 @@ mod tests {
     #[test]
     fn test_part_1() -> Result<()> {
         let res = "1320";
-        aoc::assert_output_matches_str(DAY, "example1", part_1, res)?;
+        aoc::assert_output_matches_str(DAY, "example1", |i| part_1(&mut i.as_bytes()), res)?;
         Ok(())
     }
 
     #[test]
     fn test_part_2() -> Result<()> {
         let res = "145";
-        aoc::assert_output_matches_str(DAY, "example2", part_2, res)?;
+        aoc::assert_output_matches_str(DAY, "example2", |i| part_2(&mut i.as_bytes()), res)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_other_hashes() -> Result<()> {
+        // with a single box, the lenses simply stay in insertion order
+        let input = aoc::read_input(DAY, Some("example2"))?;
+        let mut library = LensLibrary::<Fnv1a>::with_buckets(1);
+        for part in input.lines().flat_map(|l| l.split(',')) {
+            library.apply(Step::parse(part)?);
+        }
+        assert_eq!(
+            library.lenses(0).collect::<Vec<_>>(),
+            [("rn", 1), ("cm", 2), ("ot", 7), ("ab", 5), ("pc", 6)]
+        );
+        assert_eq!(Fnv1a::hash(""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(Fnv1a::hash("a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(Djb2::hash("a"), 5381 * 33 + 97);
+        Ok(())
+    }
+
+    #[test]
+    fn test_stats() -> Result<()> {
+        let stats = BucketStats::new::<AocHash>(aoc::open_input(DAY, Some("example2"))?, 256)?;
+        // rn, cm -> 0; qp -> 1; pc, ot, ab -> 3
+        assert_eq!(stats.labels, 6);
+        assert_eq!(stats.used, 3);
+        assert_eq!(stats.max_load, 3);
+        assert_eq!(stats.collisions, 1 + 3);
+        assert_eq!(
+            stats.histogram,
+            BTreeMap::from([(0, 253), (1, 1), (2, 1), (3, 1)])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_streaming() -> Result<()> {
+        // a tiny buffer, so that steps get split across reads
+        let input = aoc::read_input(DAY, Some("example2"))?;
+        let mut reader = std::io::BufReader::with_capacity(3, input.as_bytes());
+        assert_eq!(part_2(&mut reader)?, 145);
+        let mut reader = std::io::BufReader::with_capacity(3, input.as_bytes());
+        assert_eq!(part_1(&mut reader)?, 1320);
+        Ok(())
+    }
+
+    #[test]
+    fn test_trace() -> Result<()> {
+        let res = r#"After "rn=1":
+Box 0: [rn 1]
+
+After "cm-":
+Box 0: [rn 1]
+
+After "qp=3":
+Box 0: [rn 1]
+Box 1: [qp 3]
+
+After "cm=2":
+Box 0: [rn 1] [cm 2]
+Box 1: [qp 3]
+
+After "qp-":
+Box 0: [rn 1] [cm 2]
+
+After "pc=4":
+Box 0: [rn 1] [cm 2]
+Box 3: [pc 4]
+
+After "ot=9":
+Box 0: [rn 1] [cm 2]
+Box 3: [pc 4] [ot 9]
+
+After "ab=5":
+Box 0: [rn 1] [cm 2]
+Box 3: [pc 4] [ot 9] [ab 5]
+
+After "pc-":
+Box 0: [rn 1] [cm 2]
+Box 3: [ot 9] [ab 5]
+
+After "pc=6":
+Box 0: [rn 1] [cm 2]
+Box 3: [ot 9] [ab 5] [pc 6]
+
+After "ot=7":
+Box 0: [rn 1] [cm 2]
+Box 3: [ot 7] [ab 5] [pc 6]
+
+"#;
+        aoc::assert_output_matches_str(DAY, "example2", trace, res)?;
         Ok(())
     }
 }
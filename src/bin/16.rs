@@ -1,105 +1,180 @@
-use std::{fmt::Debug, mem};
+use std::{
+    cmp::Reverse,
+    fmt::{Debug, Display},
+    str::FromStr,
+};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
+use aoc::Flags;
 
 const DAY: u8 = 16;
 
 /// y, x
 type Vec2 = (usize, usize);
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum Part {
-    Blank = 0b00,
-    RLMirror = 0b10,
-    LRMirror = 0b11,
-    HPipe = 0b100,
-    VPipe = 0b110,
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum LightDir {
+    Left,
+    Right,
+    Up,
+    Down,
 }
 
-impl Part {
-    fn is_pipe(&self) -> bool {
-        return unsafe { mem::transmute(*self as u8 >> 2) };
-        // matches!(self, Self::HPipe | Self::VPipe)
+impl FromStr for LightDir {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "L" => Ok(LightDir::Left),
+            "R" => Ok(LightDir::Right),
+            "U" => Ok(LightDir::Up),
+            "D" => Ok(LightDir::Down),
+            _ => anyhow::bail!("Invalid direction: {}", s),
+        }
     }
 }
 
-fn parse_contraption(input: &str) -> Vec<Part> {
-    input
-        .lines()
-        .flat_map(|l| l.bytes())
-        .map(|b| match b {
-            b'/' => Part::LRMirror,
-            b'\\' => Part::RLMirror,
-            b'.' => Part::Blank,
-            b'-' => Part::HPipe,
-            b'|' => Part::VPipe,
-            _ => unreachable!(),
-        })
-        .collect()
+impl LightDir {
+    const ALL: [Self; 4] = [Self::Left, Self::Right, Self::Up, Self::Down];
+
+    fn inv(self) -> Self {
+        use LightDir::*;
+        match self {
+            Left => Right,
+            Right => Left,
+            Up => Down,
+            Down => Up,
+        }
+    }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
-enum LightDir {
-    Left = 0b00,
-    Right = 0b01,
-    Up = 0b10,
-    Down = 0b11,
+/// Set of beam directions leaving a tile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Beams(u8);
+
+impl Beams {
+    const NONE: Self = Self(0);
+
+    const fn of(dirs: &[LightDir]) -> Self {
+        let mut mask = 0;
+        let mut i = 0;
+        while i < dirs.len() {
+            mask |= 1 << dirs[i] as u8;
+            i += 1;
+        }
+        Self(mask)
+    }
+    fn len(self) -> u32 {
+        self.0.count_ones()
+    }
+    fn iter(self) -> impl Iterator<Item = LightDir> {
+        LightDir::ALL
+            .into_iter()
+            .filter(move |&d| self.0 & (1 << d as u8) != 0)
+    }
 }
 
-impl LightDir {
-    fn perp(self) -> (Self, Self) {
-        // // this one has the same amount of instructions -> we trust the compiler
-        // unsafe {
-        //     return mem::transmute((self as u8 ^ 0b10, self as u8 ^ 0b11));
-        // }
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Part {
+    Blank,
+    RLMirror,
+    LRMirror,
+    HPipe,
+    VPipe,
+    Absorber,
+    OneWayLeft,
+    OneWayRight,
+    OneWayUp,
+    OneWayDown,
+}
+
+impl Part {
+    const ALL: [Self; 10] = [
+        Self::Blank,
+        Self::RLMirror,
+        Self::LRMirror,
+        Self::HPipe,
+        Self::VPipe,
+        Self::Absorber,
+        Self::OneWayLeft,
+        Self::OneWayRight,
+        Self::OneWayUp,
+        Self::OneWayDown,
+    ];
+
+    /// Outgoing beams for every part (in `Part::ALL` order)
+    /// and incoming direction (in `LightDir::ALL` order).
+    ///
+    /// Every part maps the incoming directions that produce a single beam
+    /// to distinct outgoing directions, `BeamGraph` relies on that.
+    const OPTICS: [[Beams; 4]; 10] = {
         use LightDir::*;
+        const fn one(dir: LightDir) -> Beams {
+            Beams::of(&[dir])
+        }
+        const X: Beams = Beams::NONE;
+        const H: Beams = Beams::of(&[Left, Right]);
+        const V: Beams = Beams::of(&[Up, Down]);
+        [
+            // Left, Right, Up, Down
+            [one(Left), one(Right), one(Up), one(Down)],
+            [one(Up), one(Down), one(Left), one(Right)],
+            [one(Down), one(Up), one(Right), one(Left)],
+            [one(Left), one(Right), H, H],
+            [V, V, one(Up), one(Down)],
+            [X, X, X, X],
+            [one(Left), X, X, X],
+            [X, one(Right), X, X],
+            [X, X, one(Up), X],
+            [X, X, X, one(Down)],
+        ]
+    };
+
+    fn outputs(self, dir: LightDir) -> Beams {
+        Self::OPTICS[self as usize][dir as usize]
+    }
+    fn symbol(self) -> char {
         match self {
-            Left | Right => (Up, Down),
-            Up | Down => (Left, Right),
+            Part::Blank => '.',
+            Part::RLMirror => '\\',
+            Part::LRMirror => '/',
+            Part::HPipe => '-',
+            Part::VPipe => '|',
+            Part::Absorber => '#',
+            Part::OneWayLeft => '<',
+            Part::OneWayRight => '>',
+            Part::OneWayUp => '^',
+            Part::OneWayDown => 'v',
         }
     }
-    fn inv(self) -> Self {
-        return unsafe { mem::transmute(self as u8 ^ 0b01) };
-        // use LightDir::*;
-        // match self {
-        //     Left => Right,
-        //     Right => Left,
-        //     Up => Down,
-        //     Down => Up,
-        // }
-    }
-}
-
-fn is_pipe_par(pipe: Part, dir: LightDir) -> bool {
-    return dir as u8 & 0b10 == pipe as u8 & 0b10;
-    // use LightDir::*;
-    // use Part::*;
-    // match pipe {
-    //     HPipe => matches!(dir, Left | Right),
-    //     VPipe => matches!(dir, Up | Down),
-    //     _ => unreachable!(),
-    // }
-}
-
-fn reflect(dir: LightDir, part: Part) -> LightDir {
-    return unsafe { mem::transmute(dir as u8 ^ part as u8) };
-    // use LightDir::*;
-    // match part {
-    //     Part::LRMirror => match dir {
-    //         Left => Down,
-    //         Right => Up,
-    //         Up => Right,
-    //         Down => Left,
-    //     },
-    //     Part::RLMirror => match dir {
-    //         Left => Up,
-    //         Right => Down,
-    //         Up => Left,
-    //         Down => Right,
-    //     },
-    //     Part::Blank => dir,
-    //     _ => unreachable!(),
-    // }
+}
+
+impl TryFrom<u8> for Part {
+    type Error = anyhow::Error;
+
+    fn try_from(b: u8) -> Result<Self> {
+        Part::ALL
+            .into_iter()
+            .find(|p| p.symbol() == b as char)
+            .ok_or_else(|| anyhow::anyhow!("Invalid part: {}", b as char))
+    }
+}
+
+/// The parts of the contraption, row-major, and its dimensions.
+fn parse(input: &str) -> Result<(Vec<Part>, Vec2)> {
+    let dim = (
+        input.lines().count(),
+        input.lines().next().map_or(0, str::len),
+    );
+    if let Some(y) = input.lines().position(|l| l.len() != dim.1) {
+        anyhow::bail!("Line {} has a different length", y + 1);
+    }
+    let contraption = input
+        .lines()
+        .flat_map(|l| l.bytes())
+        .map(Part::try_from)
+        .collect::<Result<_>>()?;
+    Ok((contraption, dim))
 }
 
 fn get_next(dir: LightDir, dim: Vec2, curr: Vec2) -> Option<Vec2> {
@@ -123,17 +198,12 @@ struct LitData {
 }
 
 impl LitData {
-    const FULLY_LIT: u8 = 0b1111;
-
     fn new() -> Self {
         Self { _data: 0 }
     }
     fn set(&mut self, dir: LightDir) {
         self._data |= 1 << dir as u8;
     }
-    fn flit(&mut self) {
-        self._data = Self::FULLY_LIT;
-    }
     fn get(&self, dir: LightDir) -> bool {
         self._data & (1 << dir as u8) != 0
     }
@@ -171,68 +241,490 @@ impl Debug for LitData {
 fn go_through(contraption: &[Part], dim: Vec2, start: Vec2, dir: LightDir) -> Vec<LitData> {
     let mut do_next = vec![(start, dir)];
     let mut ldata = vec![LitData::new(); contraption.len()];
-    while let Some((mut pos, mut dir)) = do_next.pop() {
+    // directions the light entered every tile with
+    let mut entered = vec![LitData::new(); contraption.len()];
+    while let Some((pos, dir)) = do_next.pop() {
+        let idx = get_idx(dim, pos);
+        if entered[idx].get(dir) {
+            continue;
+        }
+        entered[idx].set(dir);
+        ldata[idx].set(dir);
+        for next_dir in contraption[idx].outputs(dir).iter() {
+            ldata[idx].set(next_dir.inv());
+            if let Some(next_pos) = get_next(next_dir, dim, pos) {
+                do_next.push((next_pos, next_dir));
+            }
+        }
+    }
+    ldata
+}
+
+fn get_energized(ldata: &[LitData]) -> usize {
+    ldata.iter().filter(|ld| !ld.is_unlit()).count()
+}
+
+#[derive(Debug, Clone)]
+struct TileSet {
+    words: Vec<u64>,
+}
+
+impl TileSet {
+    fn new(len: usize) -> Self {
+        Self {
+            words: vec![0; len.div_ceil(64)],
+        }
+    }
+    fn insert(&mut self, idx: usize) {
+        self.words[idx / 64] |= 1 << (idx % 64);
+    }
+    fn union_with(&mut self, other: &Self) {
+        for (w, o) in self.words.iter_mut().zip(&other.words) {
+            *w |= o;
+        }
+    }
+    fn clear(&mut self) {
+        self.words.fill(0);
+    }
+    fn count(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+}
+
+/// Tarjan's algorithm (without recursion).
+/// Returns the component of every node, components are numbered in reverse topological order.
+fn tarjan_scc(edges: &[Vec<usize>]) -> Vec<usize> {
+    const UNVISITED: usize = usize::MAX;
+    let mut index = vec![UNVISITED; edges.len()];
+    let mut low = vec![0; edges.len()];
+    let mut on_stack = vec![false; edges.len()];
+    let mut stack = vec![];
+    let mut comp = vec![UNVISITED; edges.len()];
+    let mut comp_count = 0;
+    let mut next_index = 0;
+    for root in 0..edges.len() {
+        if index[root] != UNVISITED {
+            continue;
+        }
+        // (node, next edge to explore)
+        let mut calls = vec![(root, 0)];
+        while let Some(&(v, e)) = calls.last() {
+            if e == 0 {
+                index[v] = next_index;
+                low[v] = next_index;
+                next_index += 1;
+                stack.push(v);
+                on_stack[v] = true;
+            }
+            if let Some(&w) = edges[v].get(e) {
+                calls.last_mut().unwrap().1 += 1;
+                if index[w] == UNVISITED {
+                    calls.push((w, 0));
+                } else if on_stack[w] {
+                    low[v] = low[v].min(index[w]);
+                }
+                continue;
+            }
+            calls.pop();
+            if let Some(&(parent, _)) = calls.last() {
+                low[parent] = low[parent].min(low[v]);
+            }
+            if low[v] == index[v] {
+                loop {
+                    let w = stack.pop().unwrap();
+                    on_stack[w] = false;
+                    comp[w] = comp_count;
+                    if w == v {
+                        break;
+                    }
+                }
+                comp_count += 1;
+            }
+        }
+    }
+    comp
+}
+
+/// Beam segments between splitters, collapsed into strongly connected components.
+///
+/// A splitter always sends out the same beams for the same split,
+/// so the tiles it energizes do not depend on where the light came from.
+/// Every tile that splits light in more than one way has a splitter per way.
+struct BeamGraph<'a> {
+    contraption: &'a [Part],
+    dim: Vec2,
+    /// splitter hit when entering a tile from every direction
+    splitter_of: Vec<[Option<usize>; 4]>,
+    /// component of every splitter
+    scc_of: Vec<usize>,
+    /// tiles energized by the splitters of every component, without the ones after it
+    own: Vec<Vec<usize>>,
+    /// components the beams of every component lead to
+    next: Vec<Vec<usize>>,
+}
+
+impl<'a> BeamGraph<'a> {
+    fn new(contraption: &'a [Part], dim: Vec2) -> Self {
+        // (tile, outgoing beams)
+        let mut splitters: Vec<(usize, Beams)> = vec![];
+        let mut splitter_of: Vec<[Option<usize>; 4]> = vec![[None; 4]; contraption.len()];
+        for (idx, part) in contraption.iter().enumerate() {
+            for dir in LightDir::ALL {
+                let outs = part.outputs(dir);
+                if outs.len() < 2 {
+                    continue;
+                }
+                let s = (0..dir as usize)
+                    .filter_map(|d| splitter_of[idx][d])
+                    .find(|&s| splitters[s].1 == outs)
+                    .unwrap_or_else(|| {
+                        splitters.push((idx, outs));
+                        splitters.len() - 1
+                    });
+                splitter_of[idx][dir as usize] = Some(s);
+            }
+        }
+        let mut graph = Self {
+            contraption,
+            dim,
+            splitter_of,
+            scc_of: vec![],
+            own: vec![],
+            next: vec![],
+        };
+        let mut tiles = vec![];
+        let mut edges = vec![];
+        for &(idx, outs) in &splitters {
+            let pos = (idx / dim.1, idx % dim.1);
+            let mut own = vec![idx];
+            let mut out = vec![];
+            for dir in outs.iter() {
+                if let Some(next) = get_next(dir, dim, pos) {
+                    let (seg, end) = graph.trace_segment(next, dir);
+                    own.extend(seg);
+                    out.extend(end);
+                }
+            }
+            tiles.push(own);
+            edges.push(out);
+        }
+        graph.scc_of = tarjan_scc(&edges);
+        let scc_count = graph.scc_of.iter().map(|&c| c + 1).max().unwrap_or(0);
+        graph.own = vec![vec![]; scc_count];
+        graph.next = vec![vec![]; scc_count];
+        for (s, (own, out)) in tiles.into_iter().zip(edges).enumerate() {
+            let c = graph.scc_of[s];
+            graph.own[c].extend(own);
+            graph.next[c].extend(out.into_iter().map(|t| graph.scc_of[t]).filter(|&t| t != c));
+        }
+        for next in &mut graph.next {
+            next.sort_unstable();
+            next.dedup();
+        }
+        graph
+    }
+
+    /// Follows the beam until it leaves the contraption, gets absorbed or split.
+    /// Returns the energized tiles (excluding the splitting one) and the splitter hit.
+    fn trace_segment(&self, start: Vec2, start_dir: LightDir) -> (Vec<usize>, Option<usize>) {
+        let mut tiles = vec![];
+        let (mut pos, mut dir) = (start, start_dir);
         loop {
-            let idx = get_idx(dim, pos);
-            let mut part = contraption[idx];
-            if part.is_pipe() && is_pipe_par(part, dir) {
-                part = Part::Blank;
+            let idx = get_idx(self.dim, pos);
+            let outs = self.contraption[idx].outputs(dir);
+            if outs.len() >= 2 {
+                return (tiles, self.splitter_of[idx][dir as usize]);
             }
-            if part.is_pipe() {
-                ldata[idx].flit();
-                let (p1, p2) = dir.perp();
-                do_next.push((pos, p1));
-                dir = p2;
-            } else {
-                let next_dir = reflect(dir, part);
-                ldata[idx].set(dir);
-                ldata[idx].set(next_dir.inv());
-                dir = next_dir;
+            tiles.push(idx);
+            let Some(next_dir) = outs.iter().next() else {
+                return (tiles, None);
+            };
+            dir = next_dir;
+            match get_next(dir, self.dim, pos) {
+                // single beams never merge, so the only possible loop goes through the start
+                Some(next) if (next, dir) != (start, start_dir) => pos = next,
+                _ => return (tiles, None),
             }
-            if let Some(next_pos) = get_next(dir, dim, pos) {
-                if ldata[get_idx(dim, next_pos)].get(dir) {
-                    break;
+        }
+    }
+
+    /// Number of energized tiles for every start.
+    fn energized_all(&self, starts: &[(Vec2, LightDir)]) -> Vec<usize> {
+        let segments = starts
+            .iter()
+            .map(|&(start, dir)| self.trace_segment(start, dir))
+            .collect::<Vec<_>>();
+        let mut reach = ReachCache::new(self, segments.iter().filter_map(|(_, end)| *end));
+        segments
+            .into_iter()
+            .map(|(seg, end)| reach.energized(self, &seg, end))
+            .collect()
+    }
+
+    /// All edge entries, most energizing first.
+    fn rank_entries(&self) -> Vec<(usize, Vec2, LightDir)> {
+        let entries = edge_entries(self.dim);
+        let mut ranking = self
+            .energized_all(&entries)
+            .into_iter()
+            .zip(entries)
+            .map(|(energized, (start, dir))| (energized, start, dir))
+            .collect::<Vec<_>>();
+        ranking.sort_by_key(|&(energized, _, _)| Reverse(energized));
+        ranking
+    }
+}
+
+/// Tiles reachable from the components some segments end in.
+///
+/// Sets are only built for components that are actually hit (and the ones after them),
+/// and dropped once every segment and component needing them has read them,
+/// so memory does not grow with the number of components.
+struct ReachCache {
+    sets: Vec<Option<TileSet>>,
+    /// reads left for the set of every component
+    uses: Vec<usize>,
+    /// scratch set for the final count
+    tiles: TileSet,
+    live: usize,
+    /// most sets alive at the same time
+    peak: usize,
+}
+
+impl ReachCache {
+    /// `ends` are the splitters hit by the segments that will be asked for, with repetitions.
+    fn new(graph: &BeamGraph, ends: impl Iterator<Item = usize>) -> Self {
+        let mut uses = vec![0; graph.own.len()];
+        let mut todo = vec![];
+        for s in ends {
+            let c = graph.scc_of[s];
+            if uses[c] == 0 {
+                todo.push(c);
+            }
+            uses[c] += 1;
+        }
+        let mut needed = vec![false; graph.own.len()];
+        todo.iter().for_each(|&c| needed[c] = true);
+        while let Some(c) = todo.pop() {
+            for &n in &graph.next[c] {
+                uses[n] += 1;
+                if !needed[n] {
+                    needed[n] = true;
+                    todo.push(n);
                 }
-                pos = next_pos;
-            } else {
-                break;
             }
         }
+        Self {
+            sets: vec![None; graph.own.len()],
+            uses,
+            tiles: TileSet::new(graph.contraption.len()),
+            live: 0,
+            peak: 0,
+        }
+    }
+
+    /// Number of tiles energized by a segment ending in the splitter `end`.
+    fn energized(&mut self, graph: &BeamGraph, seg: &[usize], end: Option<usize>) -> usize {
+        match end {
+            Some(s) => {
+                let c = graph.scc_of[s];
+                self.build(graph, c);
+                self.tiles
+                    .words
+                    .copy_from_slice(&self.sets[c].as_ref().unwrap().words);
+                self.release(c);
+            }
+            None => self.tiles.clear(),
+        }
+        seg.iter().for_each(|&t| self.tiles.insert(t));
+        self.tiles.count()
+    }
+
+    fn build(&mut self, graph: &BeamGraph, root: usize) {
+        // (component, successors built)
+        let mut stack = vec![(root, false)];
+        while let Some((c, ready)) = stack.pop() {
+            if self.sets[c].is_some() {
+                continue;
+            }
+            if !ready {
+                stack.push((c, true));
+                stack.extend(
+                    graph.next[c]
+                        .iter()
+                        .filter(|&&n| self.sets[n].is_none())
+                        .map(|&n| (n, false)),
+                );
+                continue;
+            }
+            // successors always have a lower component number, so this is a DAG
+            let mut set = TileSet::new(graph.contraption.len());
+            graph.own[c].iter().for_each(|&t| set.insert(t));
+            for &n in &graph.next[c] {
+                set.union_with(self.sets[n].as_ref().unwrap());
+                self.release(n);
+            }
+            self.sets[c] = Some(set);
+            self.live += 1;
+            self.peak = self.peak.max(self.live);
+        }
+    }
+
+    fn release(&mut self, c: usize) {
+        self.uses[c] -= 1;
+        if self.uses[c] == 0 {
+            self.sets[c] = None;
+            self.live -= 1;
+        }
     }
-    ldata
 }
 
-fn get_energized(ldata: &[LitData]) -> usize {
-    ldata.iter().filter(|ld| !ld.is_unlit()).count()
+/// Every tile on the border, with the direction pointing into the contraption.
+fn edge_entries(dim: Vec2) -> Vec<(Vec2, LightDir)> {
+    if dim.0 == 0 || dim.1 == 0 {
+        return vec![];
+    }
+    let mut entries = Vec::with_capacity(2 * (dim.0 + dim.1));
+    for x in 0..dim.1 {
+        entries.push(((0, x), LightDir::Down));
+        entries.push(((dim.0 - 1, x), LightDir::Up));
+    }
+    for y in 0..dim.0 {
+        entries.push(((y, 0), LightDir::Right));
+        entries.push(((y, dim.1 - 1), LightDir::Left));
+    }
+    entries
+}
+
+/// energized tiles as `#`, the rest as `.` (same as the puzzle text)
+fn render_energized(ldata: &[LitData], dim: Vec2) -> String {
+    let mut out = String::with_capacity(ldata.len() + dim.0);
+    for row in ldata.chunks(dim.1) {
+        out.extend(row.iter().map(|ld| if ld.is_unlit() { '.' } else { '#' }));
+        out.push('\n');
+    }
+    out
+}
+
+/// the contraption with energized tiles highlighted,
+/// optionally followed by the `LRTB` beam directions of every tile
+fn render_trace(contraption: &[Part], ldata: &[LitData], dim: Vec2, show_dirs: bool) -> String {
+    const HIGHLIGHT: &str = "\x1b[1;33m";
+    const RESET: &str = "\x1b[0m";
+    let mut out = String::new();
+    for (parts, lits) in contraption.chunks(dim.1).zip(ldata.chunks(dim.1)) {
+        for (i, (part, ld)) in parts.iter().zip(lits).enumerate() {
+            if show_dirs && i > 0 {
+                out.push(' ');
+            }
+            if ld.is_unlit() {
+                out.push(part.symbol());
+            } else {
+                out.push_str(HIGHLIGHT);
+                out.push(part.symbol());
+                out.push_str(RESET);
+            }
+            if show_dirs {
+                out.push_str(&format!("{:?}", ld));
+            }
+        }
+        out.push('\n');
+    }
+    out
 }
 
 fn part_1(input: &str) -> Result<usize> {
-    let dim = (input.lines().count(), input.lines().next().unwrap().len());
-    let contraption = parse_contraption(input);
+    let (contraption, dim) = parse(input)?;
+    if contraption.is_empty() {
+        anyhow::bail!("Empty contraption");
+    }
     let ldata = go_through(&contraption, dim, (0, 0), LightDir::Right);
     Ok(get_energized(&ldata))
 }
 
-fn part_2(input: &str) -> Result<usize> {
-    let dim = (input.lines().count(), input.lines().next().unwrap().len());
-    let contraption = parse_contraption(input);
-    let mut max = 0;
-    for x in 0..dim.1 {
-        let mut ldata = go_through(&contraption, dim, (0, x), LightDir::Down);
-        max = max.max(get_energized(&ldata));
-        ldata = go_through(&contraption, dim, (dim.1 - 1, x), LightDir::Up);
-        max = max.max(get_energized(&ldata));
+/// Edge entry energizing the most tiles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct BestEntry {
+    energized: usize,
+    start: Vec2,
+    dir: LightDir,
+}
+
+impl Display for BestEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} ({:?} going {:?})",
+            self.energized, self.start, self.dir
+        )
     }
-    for y in 0..dim.0 {
-        let mut ldata = go_through(&contraption, dim, (y, 0), LightDir::Right);
-        max = max.max(get_energized(&ldata));
-        ldata = go_through(&contraption, dim, (y, dim.0 - 1), LightDir::Left);
-        max = max.max(get_energized(&ldata));
+}
+
+fn part_2(input: &str) -> Result<BestEntry> {
+    let (contraption, dim) = parse(input)?;
+    let beams = BeamGraph::new(&contraption, dim);
+    let entries = edge_entries(dim);
+    // the first of equal entries, as in the ranking
+    let (energized, (start, dir)) = beams
+        .energized_all(&entries)
+        .into_iter()
+        .zip(entries)
+        .rev()
+        .max_by_key(|&(energized, _)| energized)
+        .context("Empty contraption")?;
+    Ok(BestEntry {
+        energized,
+        start,
+        dir,
+    })
+}
+
+/// `trace <y> <x> <L|R|U|D> [--dirs | --plain] [--input <suffix>]`
+fn trace(args: &[String]) -> Result<()> {
+    const USAGE: &str = "Usage: trace <y> <x> <L|R|U|D> [--dirs | --plain] [--input <suffix>]";
+    let flags = Flags::parse(args, &["--input"], &["--dirs", "--plain"], USAGE)?;
+    let [y, x, dir] = flags.positional()?;
+    let (show_dirs, plain) = (flags.switch("--dirs"), flags.switch("--plain"));
+    if show_dirs && plain {
+        return flags.usage();
+    }
+    let start: Vec2 = (y.parse()?, x.parse()?);
+    let dir: LightDir = dir.parse()?;
+    let input = aoc::read_input(DAY, flags.input())?;
+    let (contraption, dim) = parse(&input)?;
+    if start.0 >= dim.0 || start.1 >= dim.1 {
+        anyhow::bail!("Start {:?} is outside of the contraption {:?}", start, dim);
+    }
+    let ldata = go_through(&contraption, dim, start, dir);
+    if plain {
+        print!("{}", render_energized(&ldata, dim));
+    } else {
+        print!("{}", render_trace(&contraption, &ldata, dim, show_dirs));
+    }
+    println!("Energized: {}", get_energized(&ldata));
+    Ok(())
+}
+
+/// `rank [--input <suffix>]`
+fn rank(args: &[String]) -> Result<()> {
+    let flags = Flags::parse(args, &["--input"], &[], "Usage: rank [--input <suffix>]")?;
+    flags.positional::<0>()?;
+    let input = aoc::read_input(DAY, flags.input())?;
+    let (contraption, dim) = parse(&input)?;
+    let beams = BeamGraph::new(&contraption, dim);
+    for (i, (energized, start, dir)) in beams.rank_entries().into_iter().enumerate() {
+        println!("{:>4}. {:?} going {:?}: {}", i + 1, start, dir, energized);
     }
-    Ok(max)
+    Ok(())
 }
 
 fn main() -> Result<()> {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    match args.first().map(String::as_str) {
+        Some("trace") => return trace(&args[1..]),
+        Some("rank") => return rank(&args[1..]),
+        _ => {}
+    }
     aoc::solve_all(DAY, part_1, part_2)?;
     Ok(())
 }
@@ -250,8 +742,141 @@ mod tests {
 
     #[test]
     fn test_part_2() -> Result<()> {
-        let res = "51";
+        let res = "51 ((0, 3) going Down)";
         aoc::assert_output_matches_str(DAY, "example2", part_2, res)?;
         Ok(())
     }
+
+    #[test]
+    fn test_parse_empty_and_ragged() -> Result<()> {
+        assert_eq!(parse("")?, (vec![], (0, 0)));
+        assert!(part_1("").is_err());
+        assert!(part_2("").is_err());
+        assert!(BeamGraph::new(&[], (0, 0)).rank_entries().is_empty());
+        assert!(parse(".|.\n..\n").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_part_2_non_square() -> Result<()> {
+        let res = "11 ((0, 2) going Down)";
+        aoc::assert_output_matches_str(DAY, "example3", part_2, res)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_best_entry() -> Result<()> {
+        let input = aoc::read_input(DAY, Some("example2"))?;
+        let (contraption, dim) = parse(&input)?;
+        let ranking = BeamGraph::new(&contraption, dim).rank_entries();
+        assert_eq!(ranking.len(), 40);
+        assert_eq!(ranking[0], (51, (0, 3), LightDir::Down));
+        Ok(())
+    }
+
+    #[test]
+    fn test_optics() -> Result<()> {
+        // part, outgoing beams for incoming Left, Right, Up, Down
+        let expected = [
+            ('.', ["L", "R", "U", "D"]),
+            ('\\', ["U", "D", "L", "R"]),
+            ('/', ["D", "U", "R", "L"]),
+            ('-', ["L", "R", "LR", "LR"]),
+            ('|', ["UD", "UD", "U", "D"]),
+            ('#', ["", "", "", ""]),
+            ('<', ["L", "", "", ""]),
+            ('>', ["", "R", "", ""]),
+            ('^', ["", "", "U", ""]),
+            ('v', ["", "", "", "D"]),
+        ];
+        assert_eq!(expected.len(), Part::ALL.len());
+        for (symbol, outs) in expected {
+            let part = Part::try_from(symbol as u8)?;
+            assert_eq!(part.symbol(), symbol);
+            for (dir, out) in LightDir::ALL.into_iter().zip(outs) {
+                let out = out
+                    .chars()
+                    .map(|c| c.to_string().parse())
+                    .collect::<Result<Vec<LightDir>>>()?;
+                assert_eq!(
+                    part.outputs(dir).iter().collect::<Vec<_>>(),
+                    out,
+                    "{symbol} going {dir:?}"
+                );
+            }
+        }
+        assert!(Part::try_from(b'x').is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_beam_graph_matches_simulation() -> Result<()> {
+        let mut inputs = ["example2", "example3", "example4"]
+            .into_iter()
+            .map(|suffix| aoc::read_input(DAY, Some(suffix)))
+            .collect::<Result<Vec<_>>>()?;
+        inputs.push(aoc::generate::generate(DAY, 30, 1)?);
+        for input in inputs {
+            let (contraption, dim) = parse(&input)?;
+            let starts = (0..dim.0)
+                .flat_map(|y| (0..dim.1).map(move |x| (y, x)))
+                .flat_map(|pos| LightDir::ALL.map(|dir| (pos, dir)))
+                .collect::<Vec<_>>();
+            let energized = BeamGraph::new(&contraption, dim).energized_all(&starts);
+            for (&(start, dir), energized) in starts.iter().zip(energized) {
+                let ldata = go_through(&contraption, dim, start, dir);
+                assert_eq!(energized, get_energized(&ldata), "{start:?} going {dir:?}");
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_reach_memory_bounded() -> Result<()> {
+        let input = aoc::generate::generate(DAY, 200, 1)?;
+        let (contraption, dim) = parse(&input)?;
+        let beams = BeamGraph::new(&contraption, dim);
+        let segments = edge_entries(dim)
+            .into_iter()
+            .map(|(start, dir)| beams.trace_segment(start, dir))
+            .collect::<Vec<_>>();
+        let mut reach = ReachCache::new(&beams, segments.iter().filter_map(|(_, end)| *end));
+        for (seg, end) in &segments {
+            reach.energized(&beams, seg, *end);
+        }
+        // one full-grid set per component would be thousands of them
+        assert!(beams.own.len() > 1000);
+        assert!(reach.peak <= dim.0, "{} sets alive at once", reach.peak);
+        assert_eq!(reach.live, 0);
+        assert!(reach.sets.iter().all(Option::is_none));
+        Ok(())
+    }
+
+    #[test]
+    fn test_part_1_extended() -> Result<()> {
+        let res = "10";
+        aoc::assert_output_matches_str(DAY, "example4", part_1, res)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_energized() -> Result<()> {
+        let res = "\
+######....
+.#...#....
+.#...#####
+.#...##...
+.#...##...
+.#...##...
+.#..####..
+########..
+.#######..
+.#...#.#..
+";
+        let input = aoc::read_input(DAY, Some("example1"))?;
+        let (contraption, dim) = parse(&input)?;
+        let ldata = go_through(&contraption, dim, (0, 0), LightDir::Right);
+        assert_eq!(render_energized(&ldata, dim), res);
+        Ok(())
+    }
 }
@@ -0,0 +1,17 @@
+//! `generate <day> [--size <n>] [--seed <n>]`, prints a random input for `day`.
+
+use anyhow::Result;
+use aoc::Flags;
+
+fn main() -> Result<()> {
+    const USAGE: &str = "Usage: generate <day> [--size <n>] [--seed <n>]";
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let flags = Flags::parse(&args, &["--size", "--seed"], &[], USAGE)?;
+    let [day] = flags.positional()?;
+    let (size, seed) = (
+        flags.parsed_or("--size", 100)?,
+        flags.parsed_or("--seed", 0)?,
+    );
+    print!("{}", aoc::generate::generate(day.parse()?, size, seed)?);
+    Ok(())
+}
@@ -0,0 +1,136 @@
+//! Cycle detection for simulations that eventually repeat a state.
+//!
+//! Only a 64-bit fingerprint of every state is kept, so memory stays small
+//! no matter how big the states are, and every state is simulated once
+//! (plus one extra cycle to confirm the repeat).
+
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+};
+
+/// States `start..start + len` repeat forever.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cycle {
+    pub start: usize,
+    pub len: usize,
+}
+
+impl Cycle {
+    /// Earliest step that has the same state as step `n`.
+    pub fn reduce(&self, n: usize) -> usize {
+        if n < self.start {
+            n
+        } else {
+            self.start + (n - self.start) % self.len
+        }
+    }
+}
+
+fn fingerprint<T: Hash>(state: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    state.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Finds the cycle of the sequence `initial, step(initial), step(step(initial)), ...`.
+/// Also returns the state at `Cycle::start`.
+///
+/// A repeated fingerprint is confirmed by simulating one more cycle
+/// and comparing the full states, so the length is always right.
+/// The start relies on the fingerprint of the first state of the cycle being unique.
+///
+/// The sequence **must** be eventually periodic, otherwise this never returns.
+pub fn find_with_state<T: Clone + PartialEq + Hash>(
+    initial: &T,
+    mut step: impl FnMut(&mut T),
+) -> (Cycle, T) {
+    // first step with every fingerprint
+    let mut seen = HashMap::new();
+    let mut state = initial.clone();
+    for n in 0.. {
+        let start = *seen.entry(fingerprint(&state)).or_insert(n);
+        if start < n {
+            let len = n - start;
+            let mut ahead = state.clone();
+            for _ in 0..len {
+                step(&mut ahead);
+            }
+            if ahead == state {
+                return (Cycle { start, len }, state);
+            }
+        }
+        step(&mut state);
+    }
+    unreachable!()
+}
+
+/// Finds the cycle of the sequence `initial, step(initial), step(step(initial)), ...`.
+///
+/// The sequence **must** be eventually periodic, otherwise this never returns.
+pub fn find<T: Clone + PartialEq + Hash>(initial: &T, step: impl FnMut(&mut T)) -> Cycle {
+    find_with_state(initial, step).0
+}
+
+/// State after `n` steps, skipping all whole cycles.
+pub fn nth<T: Clone + PartialEq + Hash>(initial: T, mut step: impl FnMut(&mut T), n: usize) -> T {
+    let (cycle, mut state) = find_with_state(&initial, &mut step);
+    if n < cycle.start {
+        state = initial;
+        for _ in 0..n {
+            step(&mut state);
+        }
+        return state;
+    }
+    for _ in cycle.start..cycle.reduce(n) {
+        step(&mut state);
+    }
+    state
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(x: &mut u32) {
+        *x = (*x * *x + 1) % 255;
+    }
+
+    #[test]
+    fn test_find() {
+        // 3, 10, 101, 2, 5, 26, 167, 95, 101, ...
+        assert_eq!(find(&3, step), Cycle { start: 2, len: 6 });
+        assert_eq!(find(&101, step), Cycle { start: 0, len: 6 });
+    }
+
+    /// Hashes only the remainder modulo 7, so that different states share fingerprints.
+    #[derive(Debug, Clone, PartialEq)]
+    struct Weak(u32);
+
+    impl Hash for Weak {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            (self.0 % 7).hash(state);
+        }
+    }
+
+    #[test]
+    fn test_shared_fingerprints() {
+        // 3 and 10 share a fingerprint, but 10 does not come back after one step
+        let cycle = find(&Weak(3), |w| step(&mut w.0));
+        assert_eq!(cycle.len, 6);
+        let mut x = 3;
+        for n in 0..50 {
+            assert_eq!(nth(Weak(3), |w| step(&mut w.0), n), Weak(x));
+            step(&mut x);
+        }
+    }
+
+    #[test]
+    fn test_nth() {
+        let mut x = 3;
+        for n in 0..50 {
+            assert_eq!(nth(3, step, n), x);
+            step(&mut x);
+        }
+    }
+}
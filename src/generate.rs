@@ -0,0 +1,845 @@
+//! Random puzzle inputs, for stress tests and benchmarks.
+//!
+//! `size` is roughly the number of lines (or the side of the grid) of the input,
+//! see the generator of each day for details. The same seed always gives the same input.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+    ops::Range,
+};
+
+use anyhow::Result;
+use itertools::Itertools;
+
+/// SplitMix64, small and good enough for test inputs.
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `range`, which **must not** be empty.
+    pub fn range(&mut self, range: Range<u64>) -> u64 {
+        range.start + self.next_u64() % (range.end - range.start)
+    }
+
+    /// Uniform in `0..len`, `len` **must not** be 0.
+    pub fn index(&mut self, len: usize) -> usize {
+        self.range(0..len as u64) as usize
+    }
+
+    pub fn chance(&mut self, p: f64) -> bool {
+        ((self.next_u64() >> 11) as f64) < p * (1u64 << 53) as f64
+    }
+
+    pub fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.index(items.len())]
+    }
+
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.index(i + 1));
+        }
+    }
+}
+
+/// A random input for `day`.
+pub fn generate(day: u8, size: usize, seed: u64) -> Result<String> {
+    let mut rng = Rng::new(seed);
+    let size = size.max(1);
+    Ok(match day {
+        1 => day_01(&mut rng, size),
+        2 => day_02(&mut rng, size),
+        3 => day_03(&mut rng, size),
+        4 => day_04(&mut rng, size),
+        5 => day_05(&mut rng, size),
+        6 => day_06(&mut rng, size),
+        7 => day_07(&mut rng, size),
+        8 => day_08(&mut rng, size),
+        9 => day_09(&mut rng, size),
+        10 => day_10(&mut rng, size),
+        11 => day_11(&mut rng, size),
+        12 => day_12(&mut rng, size),
+        13 => day_13(&mut rng, size),
+        14 => day_14(&mut rng, size),
+        15 => day_15(&mut rng, size),
+        16 => day_16(&mut rng, size),
+        17 => day_17(&mut rng, size),
+        18 => day_18(&mut rng, size),
+        19 => day_19(&mut rng, size),
+        _ => anyhow::bail!("No generator for day {}", day),
+    })
+}
+
+fn random_word(rng: &mut Rng, len: Range<u64>) -> String {
+    (0..rng.range(len))
+        .map(|_| (b'a' + rng.index(26) as u8) as char)
+        .collect()
+}
+
+fn random_grid(
+    rng: &mut Rng,
+    dim: (usize, usize),
+    mut cell: impl FnMut(&mut Rng) -> char,
+) -> String {
+    let mut out = String::with_capacity(dim.0 * (dim.1 + 1));
+    for _ in 0..dim.0 {
+        out.extend((0..dim.1).map(|_| cell(rng)));
+        out.push('\n');
+    }
+    out
+}
+
+/// `size` lines of calibration values, all with at least one digit.
+fn day_01(rng: &mut Rng, size: usize) -> String {
+    const WORDS: [&str; 9] = [
+        "one", "two", "three", "four", "five", "six", "seven", "eight", "nine",
+    ];
+    let mut out = String::new();
+    for _ in 0..size {
+        let mut line = String::new();
+        while line.len() < rng.range(5..40) as usize {
+            if rng.chance(0.1) {
+                line.push((b'1' + rng.index(9) as u8) as char);
+            } else if rng.chance(0.05) {
+                let word = rng.pick(&WORDS);
+                line.push_str(word);
+            } else {
+                line.push_str(&random_word(rng, 1..2));
+            }
+        }
+        let digit = rng.index(line.len() + 1);
+        line.insert(digit, (b'1' + rng.index(9) as u8) as char);
+        writeln!(out, "{}", line).unwrap();
+    }
+    out
+}
+
+/// `size` games.
+fn day_02(rng: &mut Rng, size: usize) -> String {
+    let mut out = String::new();
+    for game in 1..=size {
+        let turns = (0..rng.range(1..7))
+            .map(|_| {
+                let mut colors = ["red", "green", "blue"];
+                rng.shuffle(&mut colors);
+                colors[..rng.range(1..4) as usize]
+                    .iter()
+                    .map(|c| format!("{} {}", rng.range(1..21), c))
+                    .join(", ")
+            })
+            .join("; ");
+        writeln!(out, "Game {}: {}", game, turns).unwrap();
+    }
+    out
+}
+
+/// `size`x`size` engine schematic.
+fn day_03(rng: &mut Rng, size: usize) -> String {
+    const SYMBOLS: &[u8] = b"*#+$/=%@&-";
+    let size = size.max(3);
+    let mut grid = vec![vec![b'.'; size]; size];
+    for row in grid.iter_mut() {
+        let mut x = 0;
+        while x < size {
+            if rng.chance(0.2) {
+                let len = (rng.range(1..4) as usize).min(size - x);
+                row[x] = b'1' + rng.index(9) as u8;
+                for b in &mut row[x + 1..x + len] {
+                    *b = b'0' + rng.index(10) as u8;
+                }
+                x += len;
+            } else if rng.chance(0.1) {
+                row[x] = *rng.pick(SYMBOLS);
+            }
+            x += 1;
+        }
+    }
+    grid.into_iter()
+        .map(|row| String::from_utf8(row).unwrap() + "\n")
+        .collect()
+}
+
+/// `size` scratchcards, none of them winning cards past the end of the table.
+fn day_04(rng: &mut Rng, size: usize) -> String {
+    let mut out = String::new();
+    for card in 0..size {
+        let mut numbers = (1..100).collect_vec();
+        rng.shuffle(&mut numbers);
+        let (winning, rest) = numbers.split_at(10);
+        // less than one match on average, so the card copies do not explode
+        let matches = match rng.range(0..100) {
+            0..=49 => 0,
+            50..=84 => 1,
+            85..=94 => 2,
+            _ => rng.range(3..11) as usize,
+        }
+        .min(size - card - 1);
+        let mut ours = winning[..matches].to_vec();
+        ours.extend_from_slice(&rest[..25 - matches]);
+        rng.shuffle(&mut ours);
+        let fmt = |nums: &[u64]| nums.iter().map(|n| format!("{:>2}", n)).join(" ");
+        writeln!(
+            out,
+            "Card {:>3}: {} | {}",
+            card + 1,
+            fmt(winning),
+            fmt(&ours)
+        )
+        .unwrap();
+    }
+    out
+}
+
+/// Almanac with `size / 4 + 1` seed ranges and `size / 2 + 1` ranges per map,
+/// all values below `1000 * size`.
+fn day_05(rng: &mut Rng, size: usize) -> String {
+    const MAPS: [&str; 7] = [
+        "seed-to-soil",
+        "soil-to-fertilizer",
+        "fertilizer-to-water",
+        "water-to-light",
+        "light-to-temperature",
+        "temperature-to-humidity",
+        "humidity-to-location",
+    ];
+    let universe = 1000 * size as u64;
+    let seeds = (0..size / 4 + 1)
+        .map(|_| {
+            let start = rng.range(0..universe);
+            format!("{} {}", start, rng.range(1..universe / 10 + 2))
+        })
+        .join(" ");
+    let mut out = format!("seeds: {}\n", seeds);
+    for name in MAPS {
+        let mut cuts = (0..2 * (size / 2 + 1))
+            .map(|_| rng.range(0..universe))
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .sorted()
+            .collect_vec();
+        if cuts.len() % 2 == 1 {
+            cuts.pop();
+        }
+        let mut lines = cuts
+            .chunks(2)
+            .map(|c| format!("{} {} {}", rng.range(0..universe), c[0], c[1] - c[0]))
+            .collect_vec();
+        rng.shuffle(&mut lines);
+        write!(out, "\n{} map:\n", name).unwrap();
+        lines.iter().for_each(|l| writeln!(out, "{}", l).unwrap());
+    }
+    out
+}
+
+/// `size` races (at most 4, so that the joined numbers of part 2 stay reasonable),
+/// every race can be won.
+fn day_06(rng: &mut Rng, size: usize) -> String {
+    let races = size.min(4);
+    loop {
+        let times = (0..races).map(|_| rng.range(7..100)).collect_vec();
+        let distances = times.iter().map(|&t| rng.range(1..t * t / 4)).collect_vec();
+        let join = |nums: &[u64]| nums.iter().join("").parse::<u64>().unwrap();
+        let (time, distance) = (join(&times), join(&distances));
+        if time * time / 4 <= distance {
+            continue;
+        }
+        let fmt = |nums: &[u64]| nums.iter().map(|n| format!("{:>6}", n)).join("");
+        return format!("Time:{}\nDistance:{}\n", fmt(&times), fmt(&distances));
+    }
+}
+
+/// `size` distinct hands (at most all of them).
+fn day_07(rng: &mut Rng, size: usize) -> String {
+    const CARDS: &[u8] = b"23456789TJQKA";
+    let mut seen = HashSet::new();
+    let mut out = String::new();
+    while seen.len() < size.min(13usize.pow(5)) {
+        let hand = (0..5).map(|_| *rng.pick(CARDS) as char).collect::<String>();
+        if seen.insert(hand.clone()) {
+            writeln!(out, "{} {}", hand, rng.range(1..1001)).unwrap();
+        }
+    }
+    out
+}
+
+/// `size` instructions and `1 + size / 20` ghosts (at most 6).
+///
+/// Every ghost walks a ring of layers and reaches its `..Z` node exactly
+/// every ring length steps, so the least common multiple is the answer.
+fn day_08(rng: &mut Rng, size: usize) -> String {
+    const PRIMES: [u64; 12] = [11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53];
+    let instructions = (0..size)
+        .map(|_| if rng.chance(0.5) { 'L' } else { 'R' })
+        .collect::<String>();
+    let mut used = HashSet::from(["AAA".to_string(), "ZZZ".to_string()]);
+    let mut name = |rng: &mut Rng, last: Option<u8>| loop {
+        let mut n = (0..3)
+            .map(|_| b'A' + rng.index(26) as u8)
+            .collect::<Vec<_>>();
+        match last {
+            Some(l) => n[2] = l,
+            None if n[2] == b'A' || n[2] == b'Z' => continue,
+            None => {}
+        }
+        let n = String::from_utf8(n).unwrap();
+        if used.insert(n.clone()) {
+            return n;
+        }
+    };
+    let mut primes = PRIMES.to_vec();
+    rng.shuffle(&mut primes);
+    let mut lines = vec![];
+    for (ghost, &len) in primes.iter().enumerate().take((1 + size / 20).min(6)) {
+        let (start, end) = if ghost == 0 {
+            ("AAA".to_string(), "ZZZ".to_string())
+        } else {
+            (name(rng, Some(b'A')), name(rng, Some(b'Z')))
+        };
+        let mut layers = (1..len)
+            .map(|_| (0..rng.range(1..3)).map(|_| name(rng, None)).collect_vec())
+            .collect_vec();
+        layers.push(vec![end]);
+        lines.push(format!(
+            "{} = ({}, {})",
+            start,
+            layers[0][0],
+            layers[0].last().unwrap()
+        ));
+        for (i, layer) in layers.iter().enumerate() {
+            let next = &layers[(i + 1) % layers.len()];
+            for node in layer {
+                lines.push(format!(
+                    "{} = ({}, {})",
+                    node,
+                    next[0],
+                    next.last().unwrap()
+                ));
+            }
+        }
+    }
+    rng.shuffle(&mut lines);
+    format!("{}\n\n{}\n", instructions, lines.join("\n"))
+}
+
+/// `size` sequences of 21 values of polynomials up to degree 4.
+fn day_09(rng: &mut Rng, size: usize) -> String {
+    let mut out = String::new();
+    for _ in 0..size {
+        let coefs = (0..=rng.range(0..5))
+            .map(|_| rng.range(0..11) as i64 - 5)
+            .collect_vec();
+        let values = (0..21i64)
+            .map(|x| coefs.iter().rev().fold(0, |acc, c| acc * x + c))
+            .join(" ");
+        writeln!(out, "{}", values).unwrap();
+    }
+    out
+}
+
+/// Cells of a random polyomino on a `dim` grid, without holes
+/// and without cells touching only by a corner.
+fn random_polyomino(rng: &mut Rng, dim: (usize, usize)) -> Vec<Vec<bool>> {
+    let mut cells = vec![vec![false; dim.1]; dim.0];
+    let mut frontier = vec![(rng.index(dim.0), rng.index(dim.1))];
+    let target = (dim.0 * dim.1 / 2).max(1);
+    let mut filled = 0;
+    while filled < target && !frontier.is_empty() {
+        let (y, x) = frontier.swap_remove(rng.index(frontier.len()));
+        if cells[y][x] {
+            continue;
+        }
+        cells[y][x] = true;
+        filled += 1;
+        if y > 0 {
+            frontier.push((y - 1, x));
+        }
+        if y + 1 < dim.0 {
+            frontier.push((y + 1, x));
+        }
+        if x > 0 {
+            frontier.push((y, x - 1));
+        }
+        if x + 1 < dim.1 {
+            frontier.push((y, x + 1));
+        }
+    }
+    // filling holes and pinches only ever adds cells, so this ends
+    loop {
+        let mut outside = vec![vec![false; dim.1 + 2]; dim.0 + 2];
+        let mut stack = vec![(0, 0)];
+        let is_cell = |y: usize, x: usize| {
+            (1..=dim.0).contains(&y) && (1..=dim.1).contains(&x) && cells[y - 1][x - 1]
+        };
+        while let Some((y, x)) = stack.pop() {
+            if outside[y][x] || is_cell(y, x) {
+                continue;
+            }
+            outside[y][x] = true;
+            if y > 0 {
+                stack.push((y - 1, x));
+            }
+            if y <= dim.0 {
+                stack.push((y + 1, x));
+            }
+            if x > 0 {
+                stack.push((y, x - 1));
+            }
+            if x <= dim.1 {
+                stack.push((y, x + 1));
+            }
+        }
+        let mut changed = false;
+        for y in 0..dim.0 {
+            for x in 0..dim.1 {
+                if !cells[y][x] && !outside[y + 1][x + 1] {
+                    cells[y][x] = true;
+                    changed = true;
+                }
+            }
+        }
+        for y in 0..dim.0.saturating_sub(1) {
+            for x in 0..dim.1.saturating_sub(1) {
+                let (a, b, c, d) = (
+                    cells[y][x],
+                    cells[y][x + 1],
+                    cells[y + 1][x],
+                    cells[y + 1][x + 1],
+                );
+                if a == d && b == c && a != b {
+                    if a {
+                        cells[y][x + 1] = true;
+                    } else {
+                        cells[y][x] = true;
+                    }
+                    changed = true;
+                }
+            }
+        }
+        if !changed {
+            return cells;
+        }
+    }
+}
+
+/// Corners of a random polyomino on a `dim` grid, clockwise (with y going down).
+/// Corner (y, x) is the top left corner of cell (y, x).
+fn random_loop(rng: &mut Rng, dim: (usize, usize)) -> Vec<(usize, usize)> {
+    let cells = random_polyomino(rng, dim);
+    let filled = |y: isize, x: isize| {
+        y >= 0
+            && x >= 0
+            && (y as usize) < dim.0
+            && (x as usize) < dim.1
+            && cells[y as usize][x as usize]
+    };
+    // boundary edges, walked with the polyomino on the right
+    let mut next = HashMap::new();
+    for y in 0..dim.0 as isize {
+        for x in 0..dim.1 as isize {
+            if !filled(y, x) {
+                continue;
+            }
+            let (cy, cx) = (y as usize, x as usize);
+            if !filled(y - 1, x) {
+                next.insert((cy, cx), (cy, cx + 1));
+            }
+            if !filled(y, x + 1) {
+                next.insert((cy, cx + 1), (cy + 1, cx + 1));
+            }
+            if !filled(y + 1, x) {
+                next.insert((cy + 1, cx + 1), (cy + 1, cx));
+            }
+            if !filled(y, x - 1) {
+                next.insert((cy + 1, cx), (cy, cx));
+            }
+        }
+    }
+    let start = *next.keys().min().unwrap();
+    let mut corners = vec![start];
+    let mut curr = next[&start];
+    while curr != start {
+        corners.push(curr);
+        curr = next[&curr];
+    }
+    corners
+}
+
+/// `size`x`size` field with a single pipe loop through `S`, and pipe junk around it.
+fn day_10(rng: &mut Rng, size: usize) -> String {
+    const PIPES: &[u8] = b"|-LJ7F";
+    let size = size.max(3);
+    let corners = random_loop(rng, (size - 1, size - 1));
+    let mut grid = vec![vec![b'.'; size]; size];
+    for _ in 0..size * size / 3 {
+        grid[rng.index(size)][rng.index(size)] = *rng.pick(PIPES);
+    }
+    for (i, &(y, x)) in corners.iter().enumerate() {
+        let prev = corners[(i + corners.len() - 1) % corners.len()];
+        let next = corners[(i + 1) % corners.len()];
+        let up = prev.0 < y || next.0 < y;
+        let down = prev.0 > y || next.0 > y;
+        let left = prev.1 < x || next.1 < x;
+        let right = prev.1 > x || next.1 > x;
+        grid[y][x] = match (up, down, left, right) {
+            (true, true, _, _) => b'|',
+            (_, _, true, true) => b'-',
+            (true, _, true, _) => b'J',
+            (true, _, _, true) => b'L',
+            (_, true, true, _) => b'7',
+            _ => b'F',
+        };
+    }
+    let (sy, sx) = *rng.pick(&corners);
+    let on_loop = corners.iter().copied().collect::<HashSet<_>>();
+    // junk next to S must not look connected to it
+    for (y, x) in [
+        (sy.wrapping_sub(1), sx),
+        (sy + 1, sx),
+        (sy, sx.wrapping_sub(1)),
+        (sy, sx + 1),
+    ] {
+        if y < size && x < size && !on_loop.contains(&(y, x)) {
+            grid[y][x] = b'.';
+        }
+    }
+    grid[sy][sx] = b'S';
+    grid.into_iter()
+        .map(|row| String::from_utf8(row).unwrap() + "\n")
+        .collect()
+}
+
+/// `size`x`size` image, with some rows and columns guaranteed empty.
+fn day_11(rng: &mut Rng, size: usize) -> String {
+    let empty_rows = (0..size).map(|_| rng.chance(0.1)).collect_vec();
+    let empty_cols = (0..size).map(|_| rng.chance(0.1)).collect_vec();
+    let mut out = String::new();
+    for &empty_row in &empty_rows {
+        for &empty_col in &empty_cols {
+            let galaxy = !empty_row && !empty_col && rng.chance(0.05);
+            out.push(if galaxy { '#' } else { '.' });
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// `size` rows, each with at least one arrangement.
+fn day_12(rng: &mut Rng, size: usize) -> String {
+    let mut out = String::new();
+    for _ in 0..size {
+        let groups = (0..rng.range(1..7)).map(|_| rng.range(1..6)).collect_vec();
+        let mut springs = ".".repeat(rng.range(0..4) as usize);
+        for (i, &g) in groups.iter().enumerate() {
+            if i > 0 {
+                springs.push_str(&".".repeat(rng.range(1..4) as usize));
+            }
+            springs.push_str(&"#".repeat(g as usize));
+        }
+        springs.push_str(&".".repeat(rng.range(0..4) as usize));
+        let springs = springs
+            .chars()
+            .map(|c| if rng.chance(0.5) { '?' } else { c })
+            .collect::<String>();
+        writeln!(out, "{} {}", springs, groups.iter().join(",")).unwrap();
+    }
+    out
+}
+
+/// Differences of every reflection line of a pattern,
+/// as (horizontal, line after this many rows/columns, differences).
+fn reflection_diffs(grid: &[Vec<u8>]) -> Vec<(bool, usize, usize)> {
+    let (h, w) = (grid.len(), grid[0].len());
+    let mut res = vec![];
+    for p in 1..h {
+        let diff = (0..p.min(h - p))
+            .map(|i| {
+                (0..w)
+                    .filter(|&x| grid[p - 1 - i][x] != grid[p + i][x])
+                    .count()
+            })
+            .sum();
+        res.push((true, p, diff));
+    }
+    for q in 1..w {
+        let diff = (0..q.min(w - q))
+            .map(|i| {
+                (0..h)
+                    .filter(|&y| grid[y][q - 1 - i] != grid[y][q + i])
+                    .count()
+            })
+            .sum();
+        res.push((false, q, diff));
+    }
+    res
+}
+
+/// `size` patterns, each with exactly one reflection line
+/// and exactly one line that reflects after fixing a smudge.
+fn day_13(rng: &mut Rng, size: usize) -> String {
+    let mut patterns = vec![];
+    while patterns.len() < size {
+        let (h, w) = (
+            rng.range(2..9) as usize * 2 + 1,
+            rng.range(2..9) as usize * 2 + 1,
+        );
+        let mut grid = (0..h)
+            .map(|_| {
+                (0..w)
+                    .map(|_| if rng.chance(0.5) { b'#' } else { b'.' })
+                    .collect_vec()
+            })
+            .collect_vec();
+        // vertical line after column q, horizontal line after row p
+        let q = rng.range(1..w as u64) as usize;
+        let p = rng.range(1..h as u64) as usize;
+        let (j, k) = (q.min(w - q), p.min(h - p));
+        for row in grid.iter_mut() {
+            for c in 0..j {
+                row[q + c] = row[q - 1 - c];
+            }
+        }
+        for r in 0..k {
+            grid[p + r] = grid[p - 1 - r].clone();
+        }
+        // the smudge is outside of the horizontal reflection, but inside the vertical one
+        let outside = (0..h).filter(|&r| r + k < p || r >= p + k).collect_vec();
+        let y = *rng.pick(&outside);
+        let x = rng.range((q - j) as u64..(q + j) as u64) as usize;
+        grid[y][x] = if grid[y][x] == b'#' { b'.' } else { b'#' };
+        let diffs = reflection_diffs(&grid);
+        let perfect = diffs.iter().filter(|d| d.2 == 0).count();
+        let smudged = diffs.iter().filter(|d| d.2 == 1).count();
+        if perfect != 1 || smudged != 1 {
+            continue;
+        }
+        if rng.chance(0.5) {
+            grid = (0..w)
+                .map(|x| (0..h).map(|y| grid[y][x]).collect_vec())
+                .collect_vec();
+        }
+        patterns.push(
+            grid.into_iter()
+                .map(|row| String::from_utf8(row).unwrap() + "\n")
+                .collect::<String>(),
+        );
+    }
+    patterns.join("\n")
+}
+
+/// `size`x`size` platform.
+fn day_14(rng: &mut Rng, size: usize) -> String {
+    random_grid(rng, (size, size), |rng| match rng.range(0..10) {
+        0..=1 => 'O',
+        2 => '#',
+        _ => '.',
+    })
+}
+
+/// `size` steps, using `size / 4 + 1` different labels.
+fn day_15(rng: &mut Rng, size: usize) -> String {
+    let labels = (0..size / 4 + 1)
+        .map(|_| random_word(rng, 2..7))
+        .collect_vec();
+    let steps = (0..size)
+        .map(|_| {
+            let label = rng.pick(&labels);
+            if rng.chance(0.3) {
+                format!("{}-", label)
+            } else {
+                format!("{}={}", label, rng.range(1..10))
+            }
+        })
+        .join(",");
+    steps + "\n"
+}
+
+/// `size`x`size` contraption.
+fn day_16(rng: &mut Rng, size: usize) -> String {
+    random_grid(rng, (size, size), |rng| {
+        if rng.chance(0.8) {
+            '.'
+        } else {
+            *rng.pick(&['/', '\\', '|', '-'])
+        }
+    })
+}
+
+/// `size`x`size` heat loss map (at least 5x5, so that ultra crucibles can make it).
+fn day_17(rng: &mut Rng, size: usize) -> String {
+    let size = size.max(5);
+    random_grid(rng, (size, size), |rng| (b'1' + rng.index(9) as u8) as char)
+}
+
+/// Dig plan along a random polyomino on a `size`x`size` grid,
+/// scaled differently for the lengths and the colors.
+/// Color steps are small enough that even a run across the whole grid fits in 5 hex digits.
+fn day_18(rng: &mut Rng, size: usize) -> String {
+    let corners = random_loop(rng, (size, size));
+    let mut scale = |max_step: u64| {
+        let mut coords = vec![0; size + 1];
+        for i in 1..=size {
+            coords[i] = coords[i - 1] + rng.range(1..max_step + 1);
+        }
+        coords
+    };
+    let (ys, xs) = (scale(10), scale(10));
+    let big_step = (0xf_ffff / size as u64).max(1);
+    let (big_ys, big_xs) = (scale(big_step), scale(big_step));
+    // (direction, length, color length), merging straight runs
+    let mut plan: Vec<(usize, u64, u64)> = vec![];
+    for (i, &(y, x)) in corners.iter().enumerate() {
+        let (ny, nx) = corners[(i + 1) % corners.len()];
+        let (dir, len, big_len) = match (ny.cmp(&y), nx.cmp(&x)) {
+            (_, std::cmp::Ordering::Greater) => (0, xs[nx] - xs[x], big_xs[nx] - big_xs[x]),
+            (std::cmp::Ordering::Greater, _) => (1, ys[ny] - ys[y], big_ys[ny] - big_ys[y]),
+            (_, std::cmp::Ordering::Less) => (2, xs[x] - xs[nx], big_xs[x] - big_xs[nx]),
+            _ => (3, ys[y] - ys[ny], big_ys[y] - big_ys[ny]),
+        };
+        match plan.last_mut() {
+            Some(last) if last.0 == dir => {
+                last.1 += len;
+                last.2 += big_len;
+            }
+            _ => plan.push((dir, len, big_len)),
+        }
+    }
+    if plan.len() > 1 && plan[0].0 == plan.last().unwrap().0 {
+        let (_, len, big_len) = plan.pop().unwrap();
+        plan[0].1 += len;
+        plan[0].2 += big_len;
+    }
+    plan.into_iter()
+        .map(|(dir, len, big_len)| {
+            format!(
+                "{} {} (#{:05x}{})\n",
+                ["R", "D", "L", "U"][dir],
+                len,
+                big_len,
+                dir
+            )
+        })
+        .collect()
+}
+
+/// About `size` workflows (a tree rooted at `in`) and `size` parts.
+fn day_19(rng: &mut Rng, size: usize) -> String {
+    let mut used = HashSet::from(["in".to_string()]);
+    let mut queue = vec!["in".to_string()];
+    let mut budget = size;
+    let mut workflows = vec![];
+    while let Some(name) = queue.pop() {
+        let mut target = |rng: &mut Rng| {
+            if budget > 0 && rng.chance(0.5) {
+                budget -= 1;
+                loop {
+                    let new = random_word(rng, 2..4);
+                    if used.insert(new.clone()) {
+                        queue.push(new.clone());
+                        return new;
+                    }
+                }
+            } else if rng.chance(0.5) {
+                "A".to_string()
+            } else {
+                "R".to_string()
+            }
+        };
+        let mut rules = (0..rng.range(1..5))
+            .map(|_| {
+                format!(
+                    "{}{}{}:{}",
+                    *rng.pick(&['x', 'm', 'a', 's']),
+                    *rng.pick(&['<', '>']),
+                    rng.range(2..4000),
+                    target(rng)
+                )
+            })
+            .collect_vec();
+        rules.push(target(rng));
+        workflows.push(format!("{}{{{}}}", name, rules.join(",")));
+    }
+    rng.shuffle(&mut workflows);
+    let parts = (0..size)
+        .map(|_| {
+            format!(
+                "{{x={},m={},a={},s={}}}",
+                rng.range(1..4001),
+                rng.range(1..4001),
+                rng.range(1..4001),
+                rng.range(1..4001)
+            )
+        })
+        .join("\n");
+    format!("{}\n\n{}\n", workflows.join("\n"), parts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deterministic() -> Result<()> {
+        for day in 1..=19 {
+            assert_eq!(generate(day, 30, 7)?, generate(day, 30, 7)?);
+        }
+        assert!(generate(20, 30, 7).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_day_18_loops_close() -> Result<()> {
+        for (size, seed) in [
+            (1, 0),
+            (10, 3),
+            (100, 1),
+            (100, 2),
+            (100, 3),
+            (100, 4),
+            (300, 5),
+        ] {
+            let (mut pos, mut color_pos) = ((0i64, 0i64), (0i64, 0i64));
+            for line in generate(18, size, seed)?.lines() {
+                let (dir, len, color) = line.split(' ').collect_tuple().unwrap();
+                let step = |dir| match dir {
+                    "R" | "0" => (0, 1),
+                    "D" | "1" => (1, 0),
+                    "L" | "2" => (0, -1),
+                    _ => (-1, 0),
+                };
+                let (dy, dx) = step(dir);
+                let len: i64 = len.parse()?;
+                pos = (pos.0 + dy * len, pos.1 + dx * len);
+                let (color_len, color_dir) = color[2..8].split_at(5);
+                let (dy, dx) = step(color_dir);
+                let color_len = i64::from_str_radix(color_len, 16)?;
+                color_pos = (color_pos.0 + dy * color_len, color_pos.1 + dx * color_len);
+            }
+            assert_eq!(pos, (0, 0), "size {size}, seed {seed}");
+            assert_eq!(color_pos, (0, 0), "size {size}, seed {seed}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_random_loop() {
+        for seed in 0..50 {
+            let corners = random_loop(&mut Rng::new(seed), (7, 9));
+            assert_eq!(corners.iter().collect::<HashSet<_>>().len(), corners.len());
+            for (a, b) in corners.iter().circular_tuple_windows() {
+                assert_eq!(a.0.abs_diff(b.0) + a.1.abs_diff(b.1), 1);
+            }
+        }
+    }
+}
@@ -1,16 +1,25 @@
-use std::{fmt::Display, fs::File, io::Read, path::PathBuf};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    fs::File,
+    io::{BufRead, BufReader, Read},
+    path::PathBuf,
+    str::FromStr,
+};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use project_root::get_project_root;
 
+pub mod cycle;
+pub mod generate;
+pub mod par;
+
 pub fn solve_all<T1: Display, T2: Display>(
     task_num: u8,
     part_1: fn(&str) -> Result<T1>,
     part_2: fn(&str) -> Result<T2>,
 ) -> Result<()> {
-    let mut input_file = File::open(get_input_path(task_num, None)?)?;
-    let mut input = String::new();
-    input_file.read_to_string(&mut input)?;
+    let input = read_input(task_num, None)?;
     let start = std::time::Instant::now();
     let res1 = part_1(&input)?;
     eprintln!("Part 1: {res1}");
@@ -21,6 +30,30 @@ pub fn solve_all<T1: Display, T2: Display>(
     Ok(())
 }
 
+/// Like `solve_all`, but every part reads the input through its own buffered reader,
+/// so the input never has to fit in memory.
+pub fn solve_all_streaming<T1: Display, T2: Display>(
+    task_num: u8,
+    part_1: fn(&mut dyn BufRead) -> Result<T1>,
+    part_2: fn(&mut dyn BufRead) -> Result<T2>,
+) -> Result<()> {
+    let input_len = get_input_path(task_num, None)?.metadata()?.len();
+    let throughput =
+        |elapsed: std::time::Duration| input_len as f64 / 1_000_000.0 / elapsed.as_secs_f64();
+    let start = std::time::Instant::now();
+    let res1 = part_1(&mut open_input(task_num, None)?)?;
+    let elapsed1 = start.elapsed();
+    eprintln!("Part 1: {res1} ({:.1} MB/s)", throughput(elapsed1));
+    let res2 = part_2(&mut open_input(task_num, None)?)?;
+    let elapsed = start.elapsed();
+    eprintln!(
+        "Part 2: {res2} ({:.1} MB/s)",
+        throughput(elapsed - elapsed1)
+    );
+    eprintln!("Finished in {:?}!", elapsed);
+    Ok(())
+}
+
 pub fn get_input_path(task_num: u8, suffix: Option<&str>) -> Result<PathBuf> {
     let mut path = get_project_root()?;
     path.push("data");
@@ -34,17 +67,167 @@ pub fn get_input_path(task_num: u8, suffix: Option<&str>) -> Result<PathBuf> {
     Ok(path)
 }
 
+pub fn read_input(task_num: u8, suffix: Option<&str>) -> Result<String> {
+    let mut input_file = File::open(get_input_path(task_num, suffix)?)?;
+    let mut input = String::new();
+    input_file.read_to_string(&mut input)?;
+    Ok(input)
+}
+
+pub fn open_input(task_num: u8, suffix: Option<&str>) -> Result<BufReader<File>> {
+    Ok(BufReader::new(File::open(get_input_path(
+        task_num, suffix,
+    )?)?))
+}
+
+/// Calls `f` with every `sep` terminated record of `reader`, reusing a single buffer.
+pub fn for_each_record(
+    mut reader: impl BufRead,
+    sep: u8,
+    mut f: impl FnMut(&str) -> Result<()>,
+) -> Result<()> {
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        if reader.read_until(sep, &mut buf)? == 0 {
+            return Ok(());
+        }
+        if buf.last() == Some(&sep) {
+            buf.pop();
+        }
+        f(std::str::from_utf8(&buf)?)?;
+    }
+}
+
 pub fn assert_output_matches_str<T: Display>(
     task_num: u8,
     file_suffix: &str,
     solve: fn(&str) -> Result<T>,
     output: &str,
 ) -> Result<()> {
-    let mut input_file = File::open(get_input_path(task_num, Some(file_suffix))?)?;
-    let mut input = String::new();
-    input_file.read_to_string(&mut input)?;
+    let input = read_input(task_num, Some(file_suffix))?;
     let res = solve(&input)?;
     let solve_output = format!("{res}");
     assert_eq!(solve_output, output);
     Ok(())
 }
+
+/// Arguments of a subcommand, in any order.
+///
+/// Arguments starting with `--` are flags: `switches` stand alone, `options` take the next
+/// argument as their value. Everything else is positional. Unknown flags, options without a
+/// value and the wrong number of positional arguments all fail with `usage`.
+pub struct Flags<'a> {
+    usage: &'a str,
+    positional: Vec<&'a str>,
+    values: HashMap<&'a str, &'a str>,
+    switches: HashSet<&'a str>,
+}
+
+impl<'a> Flags<'a> {
+    pub fn parse(
+        args: &'a [String],
+        options: &[&str],
+        switches: &[&str],
+        usage: &'a str,
+    ) -> Result<Self> {
+        let mut flags = Self {
+            usage,
+            positional: vec![],
+            values: HashMap::new(),
+            switches: HashSet::new(),
+        };
+        let mut args = args.iter().map(String::as_str);
+        while let Some(arg) = args.next() {
+            if !arg.starts_with("--") {
+                flags.positional.push(arg);
+            } else if switches.contains(&arg) {
+                flags.switches.insert(arg);
+            } else if let (true, Some(value)) = (options.contains(&arg), args.next()) {
+                flags.values.insert(arg, value);
+            } else {
+                anyhow::bail!("{}", usage);
+            }
+        }
+        Ok(flags)
+    }
+
+    /// Exactly `N` positional arguments.
+    pub fn positional<const N: usize>(&self) -> Result<[&'a str; N]> {
+        self.positional
+            .as_slice()
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("{}", self.usage))
+    }
+
+    /// Value of the last `option` given.
+    pub fn value(&self, option: &str) -> Option<&'a str> {
+        self.values.get(option).copied()
+    }
+
+    /// Parsed value of `option`, `None` if it was not given.
+    pub fn parsed<T: FromStr>(&self, option: &str) -> Result<Option<T>>
+    where
+        T::Err: std::error::Error + Send + Sync + 'static,
+    {
+        self.value(option)
+            .map(|v| {
+                v.parse()
+                    .with_context(|| format!("Invalid value for {}: {}", option, v))
+            })
+            .transpose()
+    }
+
+    pub fn parsed_or<T: FromStr>(&self, option: &str, default: T) -> Result<T>
+    where
+        T::Err: std::error::Error + Send + Sync + 'static,
+    {
+        Ok(self.parsed(option)?.unwrap_or(default))
+    }
+
+    pub fn switch(&self, switch: &str) -> bool {
+        self.switches.contains(switch)
+    }
+
+    /// Input suffix from `--input <suffix>`.
+    pub fn input(&self) -> Option<&'a str> {
+        self.value("--input")
+    }
+
+    /// Fails with the usage text.
+    pub fn usage<T>(&self) -> Result<T> {
+        anyhow::bail!("{}", self.usage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &str) -> Vec<String> {
+        s.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_flags() -> Result<()> {
+        let a = args("3 --size 7 -4 --print --input example1 --size 8");
+        let flags = Flags::parse(&a, &["--size", "--input"], &["--print"], "Usage")?;
+        assert_eq!(flags.positional()?, ["3", "-4"]);
+        assert!(flags.positional::<1>().is_err());
+        assert_eq!(flags.parsed::<usize>("--size")?, Some(8));
+        assert_eq!(flags.parsed_or("--seed", 5u64)?, 5);
+        assert_eq!(flags.input(), Some("example1"));
+        assert!(flags.switch("--print") && !flags.switch("--plain"));
+        assert!(flags.parsed::<u8>("--input").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_flags_usage() {
+        let parse = |s| Flags::parse(&args(s), &["--size"], &["--print"], "Usage").err();
+        // dangling option, unknown flag, and a switch never takes the next argument
+        assert_eq!(parse("--size").unwrap().to_string(), "Usage");
+        assert_eq!(parse("1 --seed 2").unwrap().to_string(), "Usage");
+        assert!(parse("--print 1 --size 2").is_none());
+    }
+}
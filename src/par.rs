@@ -0,0 +1,89 @@
+//! Per-line work spread over all cores.
+//!
+//! The input is cut into one chunk of whole lines per thread and the results
+//! are combined in line order, so the output is the same as with `str::lines`.
+
+use std::{iter::Sum, thread};
+
+fn thread_count() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// Splits `input` into at most `n` chunks of whole lines.
+fn line_chunks(input: &str, n: usize) -> Vec<&str> {
+    let mut chunks = Vec::with_capacity(n);
+    let mut rest = input;
+    for left in (1..=n).rev() {
+        if rest.is_empty() {
+            break;
+        }
+        let target = rest.len() / left;
+        let end = rest.as_bytes()[target..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(rest.len(), |p| target + p + 1);
+        chunks.push(&rest[..end]);
+        rest = &rest[end..];
+    }
+    chunks
+}
+
+/// Like `input.lines().map(f).collect()`, on all cores.
+pub fn map_lines<T: Send>(input: &str, f: impl Fn(&str) -> T + Sync) -> Vec<T> {
+    let f = &f;
+    thread::scope(|s| {
+        let handles = line_chunks(input, thread_count())
+            .into_iter()
+            .map(|chunk| s.spawn(move || chunk.lines().map(f).collect::<Vec<_>>()))
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().unwrap())
+            .collect()
+    })
+}
+
+/// Like `input.lines().map(f).sum()`, on all cores.
+pub fn sum_lines<T: Send + Sum>(input: &str, f: impl Fn(&str) -> T + Sync) -> T {
+    let f = &f;
+    thread::scope(|s| {
+        let handles = line_chunks(input, thread_count())
+            .into_iter()
+            .map(|chunk| s.spawn(move || chunk.lines().map(f).sum::<T>()))
+            .collect::<Vec<_>>();
+        handles.into_iter().map(|h| h.join().unwrap()).sum()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_chunks() {
+        let input = (0..100).map(|i| format!("{i}\n")).collect::<String>();
+        for n in 1..20 {
+            let chunks = line_chunks(&input, n);
+            assert!(chunks.len() <= n);
+            assert_eq!(chunks.concat(), input);
+            assert!(chunks.iter().all(|c| c.ends_with('\n')));
+        }
+        assert_eq!(line_chunks("a\nb", 4), ["a\n", "b"]);
+        assert!(line_chunks("", 4).is_empty());
+    }
+
+    #[test]
+    fn test_same_as_sequential() {
+        let input = (0..1000)
+            .map(|i| format!("{}\n", i * 7 % 13))
+            .collect::<String>();
+        let parse = |l: &str| l.parse::<u64>().unwrap();
+        assert_eq!(
+            map_lines(&input, parse),
+            input.lines().map(parse).collect::<Vec<_>>()
+        );
+        assert_eq!(sum_lines(&input, parse), input.lines().map(parse).sum());
+        let res: Result<u64, _> = sum_lines("1\nx\n3", str::parse::<u64>);
+        assert!(res.is_err());
+    }
+}